@@ -0,0 +1,59 @@
+// The daily challenge, the same board for everyone on the same day.
+
+use crate::{minesweeper::Difficulty, stats::{format_time, DailyResult}};
+
+pub const DAILY_DIFFICULTY: Difficulty = Difficulty::Normal;
+// Changing this changes every daily board, so please don't!
+const SALT: &str = "jumbledFox's minesweeper daily :3";
+
+// FNV-1a, which (unlike std's DefaultHasher) is guaranteed to be the same everywhere, forever
+pub fn seed(date: &str) -> u64 {
+    date.bytes()
+        .chain(SALT.bytes())
+        .fold(0xcbf29ce484222325, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+// A little code to show off your result with, e.g. "MSD-20261018-1:23.4-S5"
+pub fn share_code(date: &str, result: DailyResult, streak: usize) -> String {
+    let time = match result.won {
+        true  => format_time(result.time),
+        false => "LOST".to_owned(),
+    };
+    format!("MSD-{}-{}-S{}", date.replace('-', ""), time, streak)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::minesweeper::{Minesweeper, SeededRng, Tile};
+
+    // If any of these change, everyone's daily boards and codes have changed too
+
+    #[test]
+    fn seed_is_stable() {
+        assert_eq!(seed("2026-10-18"), 0x1916628e48f3f63d);
+    }
+
+    #[test]
+    fn board_is_stable() {
+        let seed = seed("2026-10-18");
+        let values = DAILY_DIFFICULTY.values();
+        let start = SeededRng::new(seed).below(values.width() * values.height());
+        assert_eq!(start, 250);
+
+        let game = Minesweeper::new_seeded(DAILY_DIFFICULTY, seed);
+        assert_eq!(game.board()[start], Tile::Dug);
+        let mut bombs: Vec<usize> = game.bombs().iter().copied().collect();
+        bombs.sort();
+        assert_eq!(bombs, [
+            9, 15, 20, 25, 32, 45, 51, 58, 64, 66, 75, 95, 106, 115, 121, 126, 136, 144, 146, 155,
+            157, 158, 163, 180, 185, 186, 189, 196, 200, 203, 210, 211, 213, 218, 230, 236, 237, 240, 241, 247,
+        ]);
+    }
+
+    #[test]
+    fn share_codes() {
+        assert_eq!(share_code("2026-10-18", DailyResult { won: true,  time: 83.42 }, 5), "MSD-20261018-1:23.4-S5");
+        assert_eq!(share_code("2026-10-18", DailyResult { won: false, time: 12.0  }, 0), "MSD-20261018-LOST-S0");
+    }
+}
@@ -0,0 +1,121 @@
+// All the text the player sees, in whatever language they've picked.
+// Each language is a json file in resources/lang with its name (in that language), how it does plurals, and a table of strings.
+// Strings can have arguments in them, "{0}", "{1}" and so on, and plurals are picked with ".one", ".few" and ".other" on the end of the key.
+// Anything a language is missing is in English instead.
+
+use std::{collections::HashMap, fmt::Display};
+
+use nanoserde::DeJson;
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+const LANGUAGES: [(&str, &str); 4] = [
+    ("en", include_str!("../resources/lang/en.json")),
+    ("fr", include_str!("../resources/lang/fr.json")),
+    ("de", include_str!("../resources/lang/de.json")),
+    ("ru", include_str!("../resources/lang/ru.json")),
+];
+
+#[derive(DeJson, Default)]
+#[nserde(default)]
+struct LangFile {
+    name:    String,
+    plurals: String,
+    strings: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Plurals {
+    // 1 is singular, everything else is plural
+    English,
+    // 0 and 1 are singular
+    French,
+    // 1, 21, 31.. / 2-4, 22-24.. / everything else
+    Russian,
+}
+
+impl Plurals {
+    pub fn from_name(name: &str) -> Option<Plurals> {match name {
+        "english" => Some(Plurals::English),
+        "french"  => Some(Plurals::French),
+        "russian" => Some(Plurals::Russian),
+        _ => None,
+    }}
+
+    fn suffix(&self, n: usize) -> &'static str {
+        match self {
+            Plurals::English => if n == 1 { "one" } else { "other" },
+            Plurals::French  => if n <= 1 { "one" } else { "other" },
+            Plurals::Russian => match (n % 10, n % 100) {
+                (1, r) if r != 11                => "one",
+                (2..=4, r) if !(12..=14).contains(&r) => "few",
+                _ => "other",
+            },
+        }
+    }
+}
+
+pub struct Lang {
+    code:    String,
+    plurals: Plurals,
+    strings: HashMap<String, String>,
+}
+
+impl Lang {
+    // Every language's code and name, for picking between them
+    pub fn list() -> Vec<(String, String)> {
+        LANGUAGES.iter()
+            .map(|(code, data)| (code.to_string(), parse(data).map(|f| f.name).unwrap_or_else(|| code.to_string())))
+            .collect()
+    }
+
+    pub fn load(code: &str) -> Option<Lang> {
+        let (code, data) = LANGUAGES.iter().find(|(c, _)| *c == code)?;
+        let file = parse(data)?;
+        let plurals = Plurals::from_name(&file.plurals).unwrap_or_else(|| {
+            macroquad::logging::error!("Language '{}' has unknown plurals '{}'!", code, file.plurals);
+            Plurals::English
+        });
+        let mut strings = file.strings;
+        // Filling in anything missing with English
+        if *code != DEFAULT_LANGUAGE {
+            let default = LANGUAGES.iter().find(|(c, _)| *c == DEFAULT_LANGUAGE).and_then(|(_, d)| parse(d));
+            for (key, value) in default.map(|f| f.strings).unwrap_or_default() {
+                strings.entry(key).or_insert(value);
+            }
+        }
+        Some(Lang { code: code.to_string(), plurals, strings })
+    }
+
+    pub fn code(&self) -> &str { &self.code }
+
+    pub fn try_get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(|s| s.as_str())
+    }
+
+    // Missing strings show up as their key, so they're easy to spot
+    pub fn get(&self, key: &str) -> String {
+        self.try_get(key).unwrap_or(key).to_owned()
+    }
+
+    pub fn format(&self, key: &str, args: &[&dyn Display]) -> String {
+        args.iter()
+            .enumerate()
+            .fold(self.get(key), |s, (i, arg)| s.replace(&format!("{{{}}}", i), &arg.to_string()))
+    }
+
+    // The first argument is n, the number that decides which plural is used
+    pub fn plural(&self, key: &str, n: usize, args: &[&dyn Display]) -> String {
+        let key = [format!("{}.{}", key, self.plurals.suffix(n)), format!("{}.other", key)]
+            .into_iter()
+            .find(|k| self.strings.contains_key(k))
+            .unwrap_or_else(|| key.to_owned());
+        let n: &dyn Display = &n;
+        self.format(&key, &[&[n], args].concat())
+    }
+}
+
+fn parse(data: &str) -> Option<LangFile> {
+    LangFile::deserialize_json(data).map_err(|e| {
+        macroquad::logging::error!("Error '{:?}' loading a language!", e);
+    }).ok()
+}
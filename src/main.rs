@@ -1,9 +1,17 @@
 use macroquad::{miniquad::{conf::Icon, window::{cancel_quit, order_quit}}, prelude::*};
-use minesweeper::{Difficulty, GameState};
-use ui::{popups::PopupKind, renderer::style, Ui};
+use lang::Lang;
+use minesweeper::{GameState, LoseCause};
+use settings::Settings;
+use stats::{today, DailyResult, GameRecord, Stats};
+use ui::{menu::{self, MenuAction}, minesweeper_element::GameMode, popups::PopupKind, renderer::{skin, sound::{SoundEffect, Volume}}, shortcuts, state::SelectedItem, Ui};
 
 pub mod ui;
 pub mod minesweeper;
+pub mod daily;
+pub mod lang;
+pub mod settings;
+pub mod stats;
+pub mod storage;
 include!(concat!(env!("OUT_DIR"), "/icon_data.rs"));
 
 fn window_conf() -> Conf {
@@ -22,104 +30,107 @@ async fn main() {
     macroquad::rand::srand(macroquad::miniquad::date::now() as _);
 
     let mut ui = Ui::new().await;
+    let mut settings = Settings::load();
+    let mut settings_unsaved = false;
+    settings.apply(&mut ui).await;
+    ui.minesweeper_element.new_game(settings.difficulty);
+    let mut stats = Stats::load();
+    let mut skins = skin::list();
+    let languages = Lang::list();
+    let mut help_open = false;
 
     loop {
         let mut quit = is_quit_requested();
         let mut new_game = None;
+        let mut new_daily = false;
 
         ui.begin();
 
-        ui.menubar.begin();
-        if ui.menubar.item("Game".to_owned(), 91.0, &mut ui.state, &mut ui.renderer) {
-            // New game
-            if ui.menubar.dropdown("New Game".to_owned(), None, &mut ui.state, &mut ui.renderer) {
+        // Look for any new skins every time the help menu's opened
+        let help_was_open = std::mem::replace(&mut help_open, ui.menubar.open_menu() == Some("menu.help"));
+        if help_open && !help_was_open {
+            skins = skin::list();
+        }
+
+        let menus = menu::menus(&ui, &skins, &languages);
+        // Any key or click could be getting bound in the controls popup, so the shortcuts and the menubar wait until it's done
+        let listening = ui.popups.listening();
+        let shortcut = shortcuts::pressed(&ui.state)
+            .filter(|_| !listening)
+            .and_then(|s| menu::shortcut_action(&menus, s));
+        if listening {
+            ui.state.hot_item = SelectedItem::Unavailable;
+        }
+        let picked = ui.menubar.show(&menus, &mut ui.state, &mut ui.renderer).filter(|_| !listening);
+
+        match shortcut.or(picked) {
+            Some(MenuAction::NewGame)    => new_game = Some(ui.minesweeper_element.difficulty()),
+            Some(MenuAction::Undo)       => { ui.minesweeper_element.undo(); }
+            Some(MenuAction::Difficulty(difficulty)) => new_game = Some(difficulty),
+            Some(MenuAction::Custom)     => ui.popups.add(PopupKind::custom(ui.minesweeper_element.custom_values()), &ui.state),
+            Some(MenuAction::Daily)      => new_daily = true,
+            Some(MenuAction::Mode(mode)) => {
+                ui.minesweeper_element.set_next_mode(mode);
                 new_game = Some(ui.minesweeper_element.difficulty());
             }
-            ui.menubar.dropdown_separator(&mut ui.renderer);
-
-            // Easy, Normal, Hard
-            let difficulties = [
-                ("Easy"  .to_owned(), "9¬¬*¬¬9¬¬,  ¬9 ¬¬".to_owned(), Difficulty::Easy),
-                ("Normal".to_owned(), "¬15*13, ¬¬40¬¬"   .to_owned(), Difficulty::Normal),
-                ("Hard"  .to_owned(), "30*16, 100"       .to_owned(), Difficulty::Hard),
-            ];
-            for (text, other_text, difficulty) in difficulties {
-                let is_current = ui.minesweeper_element.difficulty() == difficulty;
-                if ui.menubar.dropdown_radio(text, Some(other_text), is_current, &mut ui.state, &mut ui.renderer) {
-                    new_game = Some(difficulty);
-                }
-            }
-
-            // Custom
-            if ui.menubar.dropdown_radio("Custom...".to_owned(), None, ui.minesweeper_element.difficulty().is_custom(), &mut ui.state, &mut ui.renderer) {
-                ui.popups.add(PopupKind::custom(ui.minesweeper_element.custom_values()), &mut ui.state);
-            };
-            ui.menubar.dropdown_separator(&mut ui.renderer);
-
-            // Screen shake toggle
-            if ui.menubar.dropdown_radio("Screen Shake".to_owned(), None, ui.renderer.shake_enabled, &mut ui.state, &mut ui.renderer) {
-                ui.renderer.shake_enabled = !ui.renderer.shake_enabled;
+            Some(MenuAction::AutoFlag) => {
+                let mut assists = ui.minesweeper_element.assists();
+                assists.auto_flag = !assists.auto_flag;
+                ui.minesweeper_element.set_assists(assists);
             }
-            ui.menubar.dropdown_separator(&mut ui.renderer);
-
-            // Exit
-            if ui.menubar.dropdown("Exit".to_owned(), None, &mut ui.state, &mut ui.renderer) {
-                quit = true;
+            Some(MenuAction::AutoOpen) => {
+                let mut assists = ui.minesweeper_element.assists();
+                assists.auto_open = !assists.auto_open;
+                ui.minesweeper_element.set_assists(assists);
             }
-
-            ui.menubar.finish_item(&mut ui.state, &mut ui.renderer);
-        }
-        if ui.menubar.item("Help".to_owned(), 34.0, &mut ui.state, &mut ui.renderer) {
-            if ui.menubar.dropdown("Hint".to_owned(), None, &mut ui.state, &mut ui.renderer) {
-                ui.popups.add(PopupKind::Hint, &mut ui.state);
+            Some(MenuAction::Statistics)  => ui.popups.add(PopupKind::statistics(ui.minesweeper_element.difficulty(), ui.minesweeper_element.mode(), &stats), &ui.state),
+            Some(MenuAction::ScreenShake) => ui.renderer.shake_enabled = !ui.renderer.shake_enabled,
+            Some(MenuAction::Settings)    => ui.popups.add(PopupKind::settings(&settings), &ui.state),
+            Some(MenuAction::Controls)    => ui.popups.add(PopupKind::controls(&settings), &ui.state),
+            Some(MenuAction::Exit)        => quit = true,
+            Some(MenuAction::Hint)        => ui.popups.add(PopupKind::Hint, &ui.state),
+            Some(MenuAction::About)       => ui.popups.add(PopupKind::About, &ui.state),
+            Some(MenuAction::Skin(name)) => {
+                let loaded = ui.renderer.set_skin(&name).await;
+                if !loaded {
+                    macroquad::logging::error!("Couldn't load the skin '{}'!", name);
+                }
             }
-            if ui.menubar.dropdown("About".to_owned(), None, &mut ui.state, &mut ui.renderer) {
-                ui.popups.add(PopupKind::About, &mut ui.state);
+            Some(MenuAction::NumberCues) => {
+                let number_cues = ui.renderer.style().number_cues();
+                ui.renderer.style_mut().set_number_cues(!number_cues);
             }
-            ui.menubar.dropdown_separator(&mut ui.renderer);
-
-            let themes = [
-                ("Light".to_owned(), style::Theme::Light),
-                ("Dark" .to_owned(), style::Theme::Dark),
-            ];
-            for (s, theme) in themes {
-                let is_current = *ui.renderer.style().theme() == theme;
-                if ui.menubar.dropdown_radio(s, None, is_current, &mut ui.state, &mut ui.renderer) {
-                    ui.renderer.style_mut().set_theme(theme);
+            Some(MenuAction::Face(face_type)) => ui.renderer.style_mut().set_face_type(face_type),
+            Some(MenuAction::Language(code)) => {
+                let loaded = ui.renderer.set_language(&code);
+                if !loaded {
+                    macroquad::logging::error!("Couldn't load the language '{}'!", code);
                 }
             }
-            ui.menubar.dropdown_separator(&mut ui.renderer);
-
-            let faces = [
-                ("Fox" .to_owned(), style::FaceType::Fox),
-                // ("Bird".to_owned(), style::FaceType::Bird),
-                ("Nerd".to_owned(), style::FaceType::Nerd),
-            ];
-            for (s, face_type) in faces {
-                let is_current = *ui.renderer.style().face_type() == face_type;
-                if ui.menubar.dropdown_radio(s, None, is_current, &mut ui.state, &mut ui.renderer) {
-                    ui.renderer.style_mut().set_face_type(face_type);
-                }
+            Some(MenuAction::AutoScale) => ui.state.set_auto_scale(!ui.state.auto_scale()),
+            Some(MenuAction::Scale(scale)) => {
+                ui.state.set_auto_scale(false);
+                ui.state.set_scale(scale);
             }
-
-            ui.menubar.finish_item(&mut ui.state, &mut ui.renderer);
-        }
-        if ui.menubar.item("Scale".to_owned(), 28.0, &mut ui.state, &mut ui.renderer) {
-            if ui.menubar.dropdown_radio("Auto".to_owned(), None, ui.state.auto_scale(), &mut ui.state, &mut ui.renderer) {
-                ui.state.set_auto_scale(!ui.state.auto_scale());
+            Some(MenuAction::Fit) => ui.minesweeper_element.fit_minefield(),
+            Some(MenuAction::Mute) => {
+                let mut volume = ui.renderer.sound_player().volume();
+                volume.muted = !volume.muted;
+                ui.renderer.sound_player_mut().set_volume(volume);
             }
-            ui.menubar.dropdown_separator(&mut ui.renderer);
-            for i in 1..=8 {
-                if ui.menubar.dropdown_radio(format!(" {}{}* ", if i == 1 {"¬"} else {""}, i), None, ui.state.scale() == i as f32, &mut ui.state, &mut ui.renderer) {
-                    ui.state.set_auto_scale(false);
-                    ui.state.set_scale(i as f32);
+            // Clicking a volume steps it up, wrapping back round to silent
+            Some(MenuAction::Volume(category)) => {
+                let mut volume = ui.renderer.sound_player().volume();
+                match category {
+                    None           => volume.master = Volume::next_step(volume.master),
+                    Some(category) => volume.set_category(category, Volume::next_step(volume.category(category))),
                 }
+                ui.renderer.sound_player_mut().set_volume(volume);
             }
-            ui.menubar.finish_item(&mut ui.state, &mut ui.renderer);
+            None => (),
         }
-        ui.menubar.finish(&mut ui.state, &mut ui.renderer);
 
-        // TODO: Make it so popup positions change depending on the new and old scale of the window
+        ui.popups.refresh_settings(&settings);
         ui.popups.update(&mut ui.state, &ui.menubar, &mut ui.renderer);
 
         // Draw the minesweeper game below the menubar, inside the nineslice padding of the bg
@@ -131,10 +142,40 @@ async fn main() {
         );
         ui.minesweeper_element.update(minesweeper_area, &mut ui.state, &mut ui.renderer);
 
-        // Winning
-        if ui.minesweeper_element.game_state_change() == Some(GameState::Win) {
-            ui.renderer.sound_player().play_win();
-            ui.popups.add(PopupKind::Win, &mut ui.state);
+        // Recording finished games
+        if let Some(game_state) = ui.minesweeper_element.game_state_change().filter(|s| !s.is_playing()) {
+            let time = ui.minesweeper_element.timer().unwrap_or(0.0);
+
+            if game_state == GameState::Win {
+                ui.renderer.sound_player_mut().play(SoundEffect::Win);
+            }
+
+            if let Some(date) = ui.minesweeper_element.daily() {
+                // Daily challenges only count towards their own results, they start with a free opening
+                let counted = stats.record_daily(date, DailyResult { won: game_state.is_win(), time });
+                if let Some(result) = stats.daily(date) {
+                    let kind = PopupKind::Daily { date: date.to_owned(), result, counted, streak: stats.daily_streak() };
+                    ui.popups.add(kind, &ui.state);
+                }
+            } else {
+                let (record, rank) = stats.record(GameRecord::new(
+                    ui.minesweeper_element.difficulty(),
+                    ui.minesweeper_element.mode(),
+                    time,
+                    ui.minesweeper_element.boards_cleared(),
+                    ui.minesweeper_element.bbbv(),
+                    ui.minesweeper_element.game(),
+                ));
+                ui.popups.refresh_statistics(&stats);
+
+                if game_state == GameState::Win {
+                    // Winning
+                    ui.popups.add(PopupKind::win(time, record, rank), &ui.state);
+                } else if game_state == GameState::Lose(LoseCause::Timeout) {
+                    let boards_cleared = (ui.minesweeper_element.mode() == GameMode::TimeAttack).then_some(ui.minesweeper_element.boards_cleared());
+                    ui.popups.add(PopupKind::TimeUp { boards_cleared, rank }, &ui.state);
+                }
+            }
         }
 
         // Quiting
@@ -148,18 +189,39 @@ async fn main() {
         }
         
         // Making / requesting a new game 
+        if new_daily {
+            if ui.minesweeper_element.game_in_progress() {
+                ui.popups.add(PopupKind::new_daily(), &ui.state);
+            } else {
+                ui.minesweeper_element.new_daily(&today());
+            }
+        }
         if let Some(difficulty) = new_game.or_else(|| ui.minesweeper_element.new_game_requested()) {
             if ui.minesweeper_element.game_in_progress() {
-                ui.popups.add(PopupKind::NewGame { difficulty }, &mut ui.state);
+                ui.popups.add(PopupKind::new_game(difficulty), &ui.state);
             } else {
                 ui.minesweeper_element.new_game(difficulty);
             }
         }
 
-        ui.popups.handle_returns(&mut ui.minesweeper_element);
+        ui.popups.handle_returns(&mut ui.minesweeper_element, &mut stats, &ui.state);
+        if let Some(changed) = ui.popups.changed_settings() {
+            changed.apply(&mut ui).await;
+        }
 
         ui.finish();
 
+        // Save the settings whenever they've changed, but not until nothing's being held, so dragging a slider doesn't save on every frame
+        let new_settings = Settings::from_ui(&ui);
+        if new_settings != settings {
+            settings = new_settings;
+            settings_unsaved = true;
+        }
+        if settings_unsaved && ui.state.active_item.is_none() {
+            settings.save();
+            settings_unsaved = false;
+        }
+
         next_frame().await;
     }
 }
\ No newline at end of file
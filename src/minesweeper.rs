@@ -14,6 +14,8 @@ pub const MAX_WIDTH:  usize = 200;
 pub const MAX_HEIGHT: usize = 100;
 pub const MIN_WIDTH:  usize = 5;
 pub const MIN_HEIGHT: usize = 5;
+// Bombs never end the game
+pub const INFINITE_LIVES: usize = usize::MAX;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct DifficultyValues {
@@ -71,12 +73,32 @@ impl Difficulty {
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum GameState {
-    Playing, Win, Lose,
+    Playing, Win, Lose(LoseCause),
 }
 impl GameState {
     pub fn is_playing(&self) -> bool { matches!(self, Self::Playing) }
     pub fn is_win(&self)     -> bool { matches!(self, Self::Win) }
-    pub fn is_lose(&self)    -> bool { matches!(self, Self::Lose) }
+    pub fn is_lose(&self)    -> bool { matches!(self, Self::Lose(_)) }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LoseCause {
+    Bomb, Timeout,
+}
+
+// Help that does the obvious moves for the player after each of theirs
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Assists {
+    // Flags the unopened neighbours of a number when there are exactly as many of them as the number
+    pub auto_flag: bool,
+    // Digs the unopened neighbours of a number once that many of them are known to be bombs, from being hit or flagged by auto-flag
+    pub auto_open: bool,
+}
+
+impl Assists {
+    pub fn any(&self) -> bool {
+        self.auto_flag || self.auto_open
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -84,8 +106,24 @@ impl GameState {
 // TODO: Maybe bit patterns?
 pub enum Tile {
     Unopened, Flag, Dug, Numbered(u8),
+    // A bomb that was dug but didn't end the game, because there were lives left
+    Hit,
+}
+
+// What a tile was before a move changed it, and if it was one of the assists' flags
+type TileChange = (usize, Tile, bool);
+
+// Everything a move changed, kept from before it so it can be undone
+#[derive(Clone)]
+struct Move {
+    tiles:      Vec<TileChange>,
+    turns:      usize,
+    lives_used: usize,
 }
 
+// How many moves can be undone
+const UNDO_LIMIT: usize = 100;
+
 pub struct Minesweeper {
     width:      usize,
     height:     usize,
@@ -96,6 +134,24 @@ pub struct Minesweeper {
 
     state: GameState,
     turns: usize,
+    populated: bool,
+    // How many bombs can be dug before losing, normally just one
+    lives:      usize,
+    lives_used: usize,
+    assists:  Assists,
+    // The flags auto-flag placed, which unlike the player's are known to be bombs, so they're all auto-open goes by (along with hit bombs)
+    assist_flags: HashSet<usize>,
+    // Tiles that changed since the assists last looked, as only the numbers around them can have any new moves
+    assist_worklist: Vec<usize>,
+    // Goes up whenever the board changes, so it's easy to tell when it needs redrawing
+    revision: usize,
+    // If any assists were on while playing (or a move was undone), so the game can be kept out of the best times
+    assisted: bool,
+    history: Vec<Move>,
+    // The tiles the move being made has changed so far
+    changes: Vec<TileChange>,
+    // Only used by seeded games, normal ones use macroquad's random generation
+    rng: Option<SeededRng>,
     // Used in the floodfill algorithm (if you couldn't tell by the name, silly)
     // It's better to make and allocate these once rather than recreate a whole new two vectors for each flood fill operation
     floodfill_current: Vec<usize>,
@@ -115,12 +171,41 @@ impl Minesweeper {
             
             state: GameState::Playing,
             turns: 0,
+            populated: false,
+            lives:      1,
+            lives_used: 0,
+            assists:  Assists::default(),
+            assist_flags: HashSet::new(),
+            assist_worklist: Vec::new(),
+            revision: 0,
+            assisted: false,
+            history: Vec::new(),
+            changes: Vec::new(),
+            rng: None,
 
             floodfill_current: Vec::with_capacity(width * height),
             floodfill_next:    Vec::with_capacity(width * height),
         }
     }
 
+    // Makes a game where the bombs AND the first dig come from the seed, so everyone with the same seed gets exactly the same board
+    pub fn new_seeded(difficulty: Difficulty, seed: u64) -> Minesweeper {
+        let mut game = Minesweeper::new(difficulty);
+        let mut rng = SeededRng::new(seed);
+        let start = rng.below(game.board.len());
+        game.rng = Some(rng);
+        game.dig(start);
+        // The first dig was done for the player, so it doesn't count as a turn (and shouldn't start the timer)
+        game.turns = 0;
+        game
+    }
+
+    // Digging a bomb only costs a life, and the game is lost when they've all been used up
+    pub fn with_lives(mut self, lives: usize) -> Minesweeper {
+        self.lives = lives.max(1);
+        self
+    }
+
     // Getters
     pub fn width(&self)      -> usize { self.width }
     pub fn height(&self)     -> usize { self.height }
@@ -132,12 +217,60 @@ impl Minesweeper {
     pub fn state(&self) -> GameState    { self.state }
     pub fn turns(&self) -> usize        { self.turns }
 
+    pub fn assisted(&self)   -> bool  { self.assisted }
+    pub fn can_undo(&self)   -> bool  { self.state == GameState::Playing && !self.history.is_empty() }
+    pub fn revision(&self)   -> usize { self.revision }
+    pub fn lives(&self)      -> usize { self.lives }
+    pub fn lives_used(&self) -> usize { self.lives_used }
+    pub fn lives_left(&self) -> usize { self.lives.saturating_sub(self.lives_used) }
+
     // How many flags the player needs to have flagged all the bombs, if > 0, None
+    // Bombs that have been hit are already known about, so they count as flagged
     pub fn flags_left(&self) -> Option<usize> {
-        let flags_count = self.board.iter().filter(|&t| *t == Tile::Flag).count();
+        let flags_count = self.board.iter().filter(|&t| *t == Tile::Flag || *t == Tile::Hit).count();
         self.bomb_count.checked_sub(flags_count)
     }
 
+    // The minimum number of clicks needed to clear the board (without flagging or chording), known as 3BV.
+    // Each opening is one click, and so is every numbered tile that isn't on the edge of an opening.
+    // Only makes sense once the bombs have been placed!
+    pub fn bbbv(&self) -> usize {
+        let neighbours = |index: usize| NEIGHBOUR_OFFSETS
+            .iter()
+            .flat_map(move |(x, y)| get_index_from_offset(index, *x, *y, self.width, self.height));
+        let is_opening = |index: usize| !self.bombs.contains(&index) && !neighbours(index).any(|i| self.bombs.contains(&i));
+
+        let mut counted = vec![false; self.board.len()];
+        let mut openings = 0;
+        let mut stack = Vec::new();
+
+        for start in 0..self.board.len() {
+            if counted[start] || !is_opening(start) {
+                continue;
+            }
+            openings += 1;
+            counted[start] = true;
+            stack.push(start);
+            // Mark the whole opening and its border as counted
+            while let Some(index) = stack.pop() {
+                for n in neighbours(index) {
+                    if counted[n] {
+                        continue;
+                    }
+                    counted[n] = true;
+                    if is_opening(n) {
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+
+        let lone_numbers = (0..self.board.len())
+            .filter(|i| !counted[*i] && !self.bombs.contains(i))
+            .count();
+        openings + lone_numbers
+    }
+
     // Populates the minefield with bombs, making sure there are no bombs in/neighbouring safe_index
     fn populate_board(&mut self, safe_index: usize) {
         let safe_positions: Vec<usize> = NEIGHBOUR_OFFSETS
@@ -149,10 +282,17 @@ impl Minesweeper {
         let mut possible_positions: Vec<usize> = (0..self.board.len())
             .filter(|&i| !safe_positions.contains(&i))
             .collect();
-        possible_positions.shuffle();
+        match &mut self.rng {
+            // Fisher-Yates shuffle
+            Some(rng) => for i in (1..possible_positions.len()).rev() {
+                possible_positions.swap(i, rng.below(i + 1));
+            }
+            None => possible_positions.shuffle(),
+        }
         possible_positions.truncate(self.bomb_count);
 
         self.bombs = HashSet::from_iter(possible_positions);
+        self.populated = true;
     }
 
     pub fn diggable(&mut self, index: usize) -> bool {
@@ -160,20 +300,82 @@ impl Minesweeper {
         && self.board.get(index).is_some_and(|t| *t == Tile::Unopened)
     }
 
-    // Digs at a position
-    pub fn dig(&mut self, index: usize) {
+    // Turning assists on part way through a game does all the moves they would've done
+    pub fn set_assists(&mut self, assists: Assists) {
+        self.assists = assists;
+        self.assist_worklist.extend(0..self.board.len());
+        self.assist();
+    }
+
+    // Digs at a position, returning the bomb that was set off if there was one
+    pub fn dig(&mut self, index: usize) -> Option<usize> {
+        self.undoable(|game| {
+            let bomb = game.dig_tile(index);
+            bomb.or(game.assist())
+        })
+    }
+
+    // Does a move, remembering the tiles it changed if it changed any.
+    // The first dig can't be undone, as it's what places the bombs
+    fn undoable<T>(&mut self, action: impl FnOnce(&mut Self) -> T) -> T {
+        let (turns, lives_used, populated) = (self.turns, self.lives_used, self.populated);
+        self.changes.clear();
+        let result = action(self);
+        if !self.changes.is_empty() && self.populated == populated {
+            if self.history.len() == UNDO_LIMIT {
+                self.history.remove(0);
+            }
+            self.history.push(Move { tiles: std::mem::take(&mut self.changes), turns, lives_used });
+        }
+        result
+    }
+
+    // Changes a tile, remembering what it was so the move can be undone
+    fn set_tile(&mut self, index: usize, tile: Tile) {
+        let before = std::mem::replace(&mut self.board[index], tile);
+        self.changes.push((index, before, self.assist_flags.contains(&index)));
+    }
+
+    // Takes back the last move, as long as the game hasn't finished
+    pub fn undo(&mut self) -> bool {
+        if !self.can_undo() {
+            return false;
+        }
+        let Some(before) = self.history.pop() else { return false };
+        for (index, tile, assist_flag) in before.tiles.into_iter().rev() {
+            self.board[index] = tile;
+            match assist_flag {
+                true  => self.assist_flags.insert(index),
+                false => self.assist_flags.remove(&index),
+            };
+        }
+        self.turns      = before.turns;
+        self.lives_used = before.lives_used;
+        self.revision += 1;
+        self.assisted = true;
+        true
+    }
+
+    // Returns the index if there was a bomb there
+    fn dig_tile(&mut self, index: usize) -> Option<usize> {
         if !self.diggable(index) {
-            return;
+            return None;
         }
-        if self.turns == 0 {
+        if !self.populated {
             self.populate_board(index);
         }
         self.turns += 1;
+        self.revision += 1;
 
-        // We dug a bomb! lose the game and return :c
+        // We dug a bomb! use up a life, and if that was the last one lose the game and return :c
         if self.bombs.contains(&index) {
-            self.state = GameState::Lose;
-            return;
+            self.lives_used += 1;
+            match self.lives_left() {
+                0 => self.state = GameState::Lose(LoseCause::Bomb),
+                _ => self.set_tile(index, Tile::Hit),
+            }
+            self.assist_worklist.push(index);
+            return Some(index);
         }
 
         // Floodfill digging algorithm
@@ -199,6 +401,9 @@ impl Minesweeper {
                     self.floodfill_next.extend(valid_neighbours);
                 }
             }
+            // Everything the floodfill digs was unopened (and so not flagged) before
+            self.changes.extend(self.floodfill_current.iter().map(|&i| (i, Tile::Unopened, false)));
+            self.assist_worklist.extend_from_slice(&self.floodfill_current);
             if self.floodfill_next.is_empty() {
                 break;
             }
@@ -220,10 +425,22 @@ impl Minesweeper {
         if game_won {
             self.state = GameState::Win;
         }
+        None
+    }
+
+    // Ran out of time in one of the timed modes
+    pub fn time_out(&mut self) {
+        if self.state == GameState::Playing {
+            self.state = GameState::Lose(LoseCause::Timeout);
+        }
     }
 
-    // Returns if a bomb was in the chord
+    // Returns the bomb that was set off, either in the chord or by the assists afterwards
     pub fn chord(&mut self, index: usize) -> Option<usize> {
+        self.undoable(|game| game.chord_tiles(index))
+    }
+
+    fn chord_tiles(&mut self, index: usize) -> Option<usize> {
         let neighbouring_bombs = match self.board().get(index) {
             Some(Tile::Numbered(n)) if self.state() == GameState::Playing => *n as usize,
             _ => return None,
@@ -231,7 +448,7 @@ impl Minesweeper {
         let flagged_neighbour_count = NEIGHBOUR_OFFSETS
             .iter()
             .filter_map(|(x, y)| get_index_from_offset(index, *x, *y, self.width, self.height))
-            .filter(|index| matches!(self.board.get(*index), Some(Tile::Flag)))
+            .filter(|index| matches!(self.board.get(*index), Some(Tile::Flag | Tile::Hit)))
             .count();
         // If the number of bombs is the same as the number flags adjacent to this tile, chord it!
         if flagged_neighbour_count == neighbouring_bombs {
@@ -252,11 +469,75 @@ impl Minesweeper {
                 .next()
                 .cloned();
             if let Some(b) = bomb_index {
-                self.dig(b);
+                self.dig_tile(b);
+                self.assist();
                 return bomb_index;
             }
             for dig_index in &diggable_neighbours {
-                self.dig(*dig_index);
+                self.dig_tile(*dig_index);
+            }
+            return self.assist();
+        }
+        None
+    }
+
+    // Does the moves the assists are turned on for, going through the tiles that changed until there aren't any left.
+    // Returns the bomb that was set off if one was dug
+    fn assist(&mut self) -> Option<usize> {
+        if !self.assists.any() || !self.populated || self.state != GameState::Playing {
+            self.assist_worklist.clear();
+            return None;
+        }
+        self.assisted = true;
+
+        let mut around = Vec::with_capacity(NEIGHBOUR_OFFSETS.len() + 1);
+        let mut neighbours = Vec::with_capacity(NEIGHBOUR_OFFSETS.len());
+        // Every tile only gets added when it's opened or flagged, which can't happen forever
+        while let Some(changed) = self.assist_worklist.pop() {
+            // The tile itself if it's just been opened, and the numbers next to it
+            around.clear();
+            around.push(changed);
+            around.extend(NEIGHBOUR_OFFSETS.iter().flat_map(|(x, y)| get_index_from_offset(changed, *x, *y, self.width, self.height)));
+
+            for &index in &around {
+                let number = match self.board[index] {
+                    Tile::Numbered(n) => n as usize,
+                    _ => continue,
+                };
+                neighbours.clear();
+                neighbours.extend(NEIGHBOUR_OFFSETS
+                    .iter()
+                    .flat_map(|(x, y)| get_index_from_offset(index, *x, *y, self.width, self.height))
+                    .filter(|i| matches!(self.board[*i], Tile::Unopened | Tile::Flag | Tile::Hit))
+                );
+                let unopened = neighbours.iter().filter(|i| self.board[**i] == Tile::Unopened).count();
+                if unopened == 0 {
+                    continue;
+                }
+                // The player's flags might be wrong, so they're no help in knowing what's safe
+                let proven = neighbours.iter().filter(|i| self.board[**i] == Tile::Hit || self.assist_flags.contains(i)).count();
+
+                if self.assists.auto_flag && neighbours.len() == number {
+                    for &i in &neighbours {
+                        if self.board[i] == Tile::Unopened {
+                            self.set_tile(i, Tile::Flag);
+                            self.assist_flags.insert(i);
+                            self.assist_worklist.push(i);
+                        }
+                    }
+                    self.revision += 1;
+                } else if self.assists.auto_open && proven == number {
+                    for &i in &neighbours {
+                        if let Some(bomb) = self.dig_tile(i) {
+                            self.assist_worklist.clear();
+                            return Some(bomb);
+                        }
+                    }
+                }
+                if self.state != GameState::Playing {
+                    self.assist_worklist.clear();
+                    return None;
+                }
             }
         }
         None
@@ -264,18 +545,25 @@ impl Minesweeper {
 
     // Flags / unflags
     pub fn set_flag(&mut self, flag_mode: SetFlagMode, index: usize) -> bool {
+        self.undoable(|game| game.set_flag_tile(flag_mode, index))
+    }
+
+    fn set_flag_tile(&mut self, flag_mode: SetFlagMode, index: usize) -> bool {
         if self.state != GameState::Playing {
             return false;
         }
-        let tile = match self.board.get_mut(index) {
-            None => return false,
-            Some(t) => t,
+        let new_tile = match (self.board.get(index), flag_mode) {
+            (Some(Tile::Unopened), SetFlagMode::Toggle | SetFlagMode::Flag)   => Tile::Flag,
+            (Some(Tile::Flag),     SetFlagMode::Toggle | SetFlagMode::Remove) => Tile::Unopened,
+            _ => return false,
         };
-        match (&tile, flag_mode) {
-            (Tile::Unopened, SetFlagMode::Toggle | SetFlagMode::Flag)   => {*tile = Tile::Flag;     true},
-            (Tile::Flag,     SetFlagMode::Toggle | SetFlagMode::Remove) => {*tile = Tile::Unopened; true},
-            _ => false,
+        self.set_tile(index, new_tile);
+        self.revision += 1;
+        // The player's flags don't give the assists anything to go on, and taking one away means it's not known to be a bomb any more
+        if self.board[index] == Tile::Unopened {
+            self.assist_flags.remove(&index);
         }
+        true
     }
 }
 
@@ -284,6 +572,30 @@ pub enum SetFlagMode {
     Toggle, Flag, Remove
 }
 
+// A tiny random number generator (SplitMix64).
+// Unlike macroquad's, it's guaranteed to give the same numbers for the same seed on every machine.
+#[derive(Debug, Clone)]
+pub struct SeededRng(u64);
+
+impl SeededRng {
+    pub fn new(seed: u64) -> SeededRng {
+        SeededRng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    // A number from 0 up to (but not including) max
+    pub fn below(&mut self, max: usize) -> usize {
+        (self.next_u64() % max.max(1) as u64) as usize
+    }
+}
+
 pub fn get_index_from_offset(index: usize, x_offset: isize, y_offset: isize,  width: usize, height: usize) -> Option<usize> {
     let x = match (index % width).checked_add_signed(x_offset) {
         Some(x) if x < width => x,
@@ -0,0 +1,245 @@
+// Everything the player can change that should be remembered between launches.
+// It's saved as a tiny toml file, and any keys that are missing or don't make sense are just left as their defaults.
+
+use std::collections::HashMap;
+
+use nanoserde::{Toml, TomlParser};
+
+use crate::{lang::DEFAULT_LANGUAGE, minesweeper::{Assists, Difficulty}, storage, ui::{bindings::{Action, Bindings}, minesweeper_element::GameMode, renderer::{skin::DEFAULT_SKIN, sound::{SoundCategory, Volume}, style::FaceType}, Ui}};
+
+const SETTINGS_KEY: &str = "settings.toml";
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Settings {
+    pub skin:          String,
+    pub language:      String,
+    pub face_type:     FaceType,
+    pub number_cues:   bool,
+    pub auto_scale:    bool,
+    pub scale:         f32,
+    pub shake_enabled: bool,
+    pub volume:        Volume,
+    pub difficulty:    Difficulty,
+    pub mode:          GameMode,
+    pub assists:       Assists,
+    pub bindings:      Bindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            skin:          DEFAULT_SKIN.to_owned(),
+            language:      DEFAULT_LANGUAGE.to_owned(),
+            face_type:     FaceType::Fox,
+            number_cues:   false,
+            auto_scale:    true,
+            scale:         2.0,
+            shake_enabled: true,
+            volume:        Volume::default(),
+            difficulty:    Difficulty::Easy,
+            mode:          GameMode::Classic,
+            assists:       Assists::default(),
+            bindings:      Bindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Settings {
+        storage::load(SETTINGS_KEY)
+            .map(|data| Settings::parse(&data))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save(SETTINGS_KEY, &self.to_toml());
+    }
+
+    pub fn parse(data: &str) -> Settings {
+        let mut settings = Settings::default();
+        let map = match TomlParser::parse(data) {
+            Ok(m) => m,
+            Err(e) => {
+                macroquad::logging::error!("Error '{:?}' parsing the settings!", e);
+                return settings;
+            }
+        };
+
+        let string  = |key: &str| match map.get(key) { Some(Toml::Str(s))  => Some(s.as_str()), _ => None };
+        let boolean = |key: &str| match map.get(key) { Some(Toml::Bool(b)) => Some(*b),        _ => None };
+
+        if let Some(skin) = string("skin") {
+            settings.skin = skin.to_owned();
+        }
+        if let Some(language) = string("language") {
+            settings.language = language.to_owned();
+        }
+        if let Some(face_type) = string("face").and_then(FaceType::from_name) {
+            settings.face_type = face_type;
+        }
+        if let Some(number_cues) = boolean("number_cues") {
+            settings.number_cues = number_cues;
+        }
+        if let Some(auto_scale) = boolean("auto_scale") {
+            settings.auto_scale = auto_scale;
+        }
+        if let Some(scale) = number(&map, "scale").filter(|s| (1..=8).contains(s)) {
+            settings.scale = scale as f32;
+        }
+        if let Some(shake_enabled) = boolean("screen_shake") {
+            settings.shake_enabled = shake_enabled;
+        }
+        // Volumes are saved as percentages
+        let percent = |key: &str| number(&map, key).filter(|v| *v <= 100).map(|v| v as f32 / 100.0);
+        if let Some(muted) = boolean("muted") {
+            settings.volume.muted = muted;
+        }
+        if let Some(master) = percent("volume") {
+            settings.volume.master = master;
+        }
+        for category in SoundCategory::ALL {
+            if let Some(volume) = percent(&format!("volume_{}", category.name())) {
+                settings.volume.set_category(category, volume);
+            }
+        }
+        let difficulty = match string("difficulty") {
+            Some("easy")   => Some(Difficulty::Easy),
+            Some("normal") => Some(Difficulty::Normal),
+            Some("hard")   => Some(Difficulty::Hard),
+            Some("custom") => match (number(&map, "custom_width"), number(&map, "custom_height"), number(&map, "custom_bombs")) {
+                (Some(w), Some(h), Some(b)) => Difficulty::custom(w, h, b),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(difficulty) = difficulty {
+            settings.difficulty = difficulty;
+        }
+        if let Some(mode) = string("mode").and_then(GameMode::from_name) {
+            settings.mode = mode;
+        }
+        if let Some(auto_flag) = boolean("auto_flag") {
+            settings.assists.auto_flag = auto_flag;
+        }
+        if let Some(auto_open) = boolean("auto_open") {
+            settings.assists.auto_open = auto_open;
+        }
+        for action in Action::ALL {
+            if let Some(inputs) = string(&format!("controls_{}", action.name())) {
+                settings.bindings.set_from_names(action, inputs);
+            }
+        }
+        settings
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut toml = format!(
+            "skin = \"{}\"\nlanguage = \"{}\"\nface = \"{}\"\nnumber_cues = {}\nauto_scale = {}\nscale = {}\nscreen_shake = {}\nmode = \"{}\"\nauto_flag = {}\nauto_open = {}\n",
+            self.skin, self.language, self.face_type.name(), self.number_cues, self.auto_scale, self.scale, self.shake_enabled, self.mode.name(),
+            self.assists.auto_flag, self.assists.auto_open,
+        );
+        toml += &format!("muted = {}\nvolume = {}\n", self.volume.muted, (self.volume.master * 100.0).round());
+        for category in SoundCategory::ALL {
+            toml += &format!("volume_{} = {}\n", category.name(), (self.volume.category(category) * 100.0).round());
+        }
+        for action in Action::ALL {
+            toml += &format!("controls_{} = \"{}\"\n", action.name(), self.bindings.to_names(action));
+        }
+        let difficulty = match self.difficulty {
+            Difficulty::Easy   => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard   => "hard",
+            Difficulty::Custom(v) => {
+                toml += &format!("custom_width = {}\ncustom_height = {}\ncustom_bombs = {}\n", v.width(), v.height(), v.bomb_count());
+                "custom"
+            }
+        };
+        toml + &format!("difficulty = \"{}\"\n", difficulty)
+    }
+
+    // Reads the current settings out of the ui
+    pub fn from_ui(ui: &Ui) -> Settings {
+        Settings {
+            skin:          ui.renderer.style().skin().to_owned(),
+            language:      ui.renderer.lang().code().to_owned(),
+            face_type:     *ui.renderer.style().face_type(),
+            number_cues:   ui.renderer.style().number_cues(),
+            auto_scale:    ui.state.auto_scale(),
+            scale:         ui.state.scale(),
+            shake_enabled: ui.renderer.shake_enabled,
+            volume:        ui.renderer.sound_player().volume(),
+            difficulty:    ui.minesweeper_element.difficulty(),
+            mode:          ui.minesweeper_element.next_mode(),
+            assists:       ui.minesweeper_element.assists(),
+            bindings:      ui.minesweeper_element.bindings().clone(),
+        }
+    }
+
+    // Doesn't start a new game, so it can be used whenever something's changed
+    pub async fn apply(&self, ui: &mut Ui) {
+        // Skins and languages are loaded from files, so they're only loaded if they've changed
+        if ui.renderer.style().skin() != self.skin && !ui.renderer.set_skin(&self.skin).await {
+            macroquad::logging::error!("Couldn't load the skin '{}'!", self.skin);
+        }
+        if ui.renderer.lang().code() != self.language && !ui.renderer.set_language(&self.language) {
+            macroquad::logging::error!("Couldn't load the language '{}'!", self.language);
+        }
+        ui.renderer.style_mut().set_face_type(self.face_type);
+        ui.renderer.style_mut().set_number_cues(self.number_cues);
+        ui.renderer.shake_enabled = self.shake_enabled;
+        ui.renderer.sound_player_mut().set_volume(self.volume);
+        ui.state.set_auto_scale(self.auto_scale);
+        if !self.auto_scale {
+            ui.state.set_scale(self.scale);
+        }
+        ui.minesweeper_element.set_next_mode(self.mode);
+        ui.minesweeper_element.set_assists(self.assists);
+        ui.minesweeper_element.set_bindings(self.bindings.clone());
+    }
+}
+
+// Only whole, positive numbers are any use to us
+fn number(map: &HashMap<String, Toml>, key: &str) -> Option<usize> {
+    match map.get(key) {
+        Some(Toml::Num(n)) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::bindings::Preset;
+
+    #[test]
+    fn round_trip() {
+        let mut settings = Settings {
+            skin:          "dark".to_owned(),
+            language:      "de".to_owned(),
+            face_type:     FaceType::Nerd,
+            number_cues:   true,
+            auto_scale:    false,
+            scale:         3.0,
+            shake_enabled: false,
+            volume:        Volume::default(),
+            difficulty:    Difficulty::custom(20, 12, 50).unwrap(),
+            mode:          GameMode::Lives,
+            assists:       Assists { auto_flag: true, auto_open: true },
+            bindings:      Bindings::preset(Preset::LeftHanded),
+        };
+        settings.volume.muted = true;
+        settings.volume.master = 0.5;
+        settings.volume.set_category(SoundCategory::Jingles, 0.25);
+
+        let parsed = Settings::parse(&settings.to_toml());
+        // Reading the controls back groups each action's inputs together, so they're compared by how they're written
+        assert_eq!(parsed.to_toml(), settings.to_toml());
+        assert_eq!(Settings { bindings: settings.bindings.clone(), ..parsed }, settings);
+    }
+
+    #[test]
+    fn nonsense_is_ignored() {
+        let parsed = Settings::parse("scale = 40\nvolume = 250\ndifficulty = \"custom\"\ncustom_width = 1\nface = \"owl\"\n");
+        assert_eq!(parsed, Settings::default());
+    }
+}
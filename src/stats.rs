@@ -0,0 +1,275 @@
+// Keeps a record of every finished game, which the best times and the statistics popup are worked out from.
+
+use std::collections::HashMap;
+
+use nanoserde::{DeJson, SerJson};
+
+use crate::{lang::Lang, minesweeper::{Difficulty, Minesweeper}, storage, ui::minesweeper_element::GameMode};
+
+const STATS_KEY: &str = "stats.json";
+pub const BEST_TIMES_LEN: usize = 10;
+
+#[derive(Debug, Clone, DeJson, SerJson)]
+pub struct GameRecord {
+    // Stays the same whatever else is recorded or reset, so a name can be given to the right game
+    pub id:         u64,
+    pub difficulty: String,
+    pub mode:       String,
+    pub won:        bool,
+    pub time:       f32,
+    pub bbbv:       usize,
+    pub date:       String,
+    pub name:       String,
+    // How many boards were cleared in time attack
+    pub score:      usize,
+    // How many bombs were dug in lives mode
+    pub lives_used: usize,
+    // Games played with assists don't go in the best times
+    pub assisted:   bool,
+}
+
+impl GameRecord {
+    // bbbv is passed in separately as time attack adds up the boards that were cleared
+    pub fn new(difficulty: Difficulty, mode: GameMode, time: f32, score: usize, bbbv: usize, game: &Minesweeper) -> GameRecord {
+        GameRecord {
+            id:         0,
+            difficulty: stats_key(difficulty, mode),
+            mode:       mode.name().to_owned(),
+            won:        game.state().is_win(),
+            time,
+            bbbv,
+            date:       today(),
+            name:       String::new(),
+            score,
+            lives_used: game.lives_used(),
+            assisted:   game.assisted(),
+        }
+    }
+
+    pub fn mode(&self) -> GameMode {
+        GameMode::from_name(&self.mode).unwrap_or(GameMode::Classic)
+    }
+}
+
+// How a day's daily challenge went, only the first go each day counts
+#[derive(Debug, Clone, Copy, DeJson, SerJson)]
+pub struct DailyResult {
+    pub won:  bool,
+    pub time: f32,
+}
+
+#[derive(Default, DeJson, SerJson)]
+#[nserde(default)]
+pub struct Stats {
+    games: Vec<GameRecord>,
+    // Keyed by date
+    daily: HashMap<String, DailyResult>,
+    // The id the next record gets, which isn't reset along with everything else
+    next_id: u64,
+}
+
+// Everything the statistics popup shows for a single difficulty
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub played:         usize,
+    pub won:            usize,
+    pub current_streak: usize,
+    pub best_streak:    usize,
+    pub best:           Vec<GameRecord>,
+}
+
+impl Summary {
+    pub fn win_percentage(&self) -> usize {
+        match self.played {
+            0 => 0,
+            p => (self.won * 100 + p / 2) / p,
+        }
+    }
+}
+
+impl Stats {
+    pub fn load() -> Stats {
+        let data = match storage::load(STATS_KEY) {
+            Some(d) => d,
+            None => return Stats::default(),
+        };
+        match Stats::deserialize_json(&data) {
+            Ok(stats) => stats,
+            Err(e) => {
+                macroquad::logging::error!("Error '{:?}' loading the statistics!", e);
+                Stats::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        storage::save(STATS_KEY, &self.serialize_json());
+    }
+
+    // Returns the id of the record, and where it placed in the best games if it made it in
+    pub fn record(&mut self, mut record: GameRecord) -> (u64, Option<usize>) {
+        let rank = {
+            let games = self.games_with_key(&record.difficulty).filter(|g| !g.assisted);
+            match (record.won, record.mode() == GameMode::TimeAttack) {
+                _ if record.assisted          => None,
+                (_, true) if record.score > 0 => Some(games.filter(|g| g.score >= record.score).count() + 1),
+                (true, false)                 => Some(games.filter(|g| g.won && g.time <= record.time).count() + 1),
+                _ => None,
+            }
+        };
+        record.id = self.next_id;
+        self.next_id += 1;
+        let id = record.id;
+        self.games.push(record);
+        self.save();
+        (id, rank.filter(|r| *r <= BEST_TIMES_LEN))
+    }
+
+    pub fn set_name(&mut self, id: u64, name: String) {
+        if let Some(game) = self.games.iter_mut().find(|g| g.id == id) {
+            game.name = name;
+            self.save();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.games.clear();
+        self.daily.clear();
+        self.save();
+    }
+
+    // Returns false if this day's challenge has already been done
+    pub fn record_daily(&mut self, date: &str, result: DailyResult) -> bool {
+        if self.daily.contains_key(date) {
+            return false;
+        }
+        self.daily.insert(date.to_owned(), result);
+        self.save();
+        true
+    }
+
+    pub fn daily(&self, date: &str) -> Option<DailyResult> {
+        self.daily.get(date).copied()
+    }
+
+    // How many days in a row the daily challenge has been won, up to today.
+    // Not having played today yet doesn't break the streak.
+    pub fn daily_streak(&self) -> usize {
+        let today = today_days();
+        let won = |days: i64| self.daily.get(&date_string(days)).is_some_and(|r| r.won);
+        let start = if self.daily.contains_key(&date_string(today)) { today } else { today - 1 };
+        (0..).take_while(|i| won(start - i)).count()
+    }
+
+    // The standard difficulties, then every custom one that's been played
+    pub fn difficulties(&self) -> Vec<Difficulty> {
+        let mut custom: Vec<(usize, usize, usize)> = self.games.iter().filter_map(|g| custom_values(&g.difficulty)).collect();
+        custom.sort();
+        custom.dedup();
+        [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard].into_iter()
+            .chain(custom.into_iter().filter_map(|(w, h, b)| Difficulty::custom(w, h, b)))
+            .collect()
+    }
+
+    fn games_with_key<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a GameRecord> {
+        self.games.iter().filter(move |g| g.difficulty == key)
+    }
+
+    // The fastest wins, quickest first.
+    // In time attack nobody wins, so it's the games with the most boards cleared instead
+    pub fn best(&self, difficulty: Difficulty, mode: GameMode) -> Vec<GameRecord> {
+        let key = stats_key(difficulty, mode);
+        let mut best: Vec<GameRecord> = match mode {
+            GameMode::TimeAttack => self.games_with_key(&key).filter(|g| !g.assisted && g.score > 0).cloned().collect(),
+            _                    => self.games_with_key(&key).filter(|g| !g.assisted && g.won)      .cloned().collect(),
+        };
+        best.sort_by(|a, b| b.score.cmp(&a.score).then(a.time.total_cmp(&b.time)));
+        best.truncate(BEST_TIMES_LEN);
+        best
+    }
+
+    pub fn summary(&self, difficulty: Difficulty, mode: GameMode) -> Summary {
+        let key = stats_key(difficulty, mode);
+        let mut summary = Summary { best: self.best(difficulty, mode), ..Default::default() };
+        for game in self.games_with_key(&key) {
+            summary.played += 1;
+            if game.won {
+                summary.won += 1;
+                summary.current_streak += 1;
+                summary.best_streak = summary.best_streak.max(summary.current_streak);
+            } else {
+                summary.current_streak = 0;
+            }
+        }
+        summary
+    }
+}
+
+// Custom games are only compared with others of exactly the same size and bomb count
+pub fn difficulty_key(difficulty: Difficulty) -> String {
+    match difficulty {
+        Difficulty::Easy   => "easy".to_owned(),
+        Difficulty::Normal => "normal".to_owned(),
+        Difficulty::Hard   => "hard".to_owned(),
+        Difficulty::Custom(v) => format!("custom {}x{} {}", v.width(), v.height(), v.bomb_count()),
+    }
+}
+
+// The width, height and bomb count back out of a custom game's key
+fn custom_values(key: &str) -> Option<(usize, usize, usize)> {
+    let mut parts = key.strip_prefix("custom ")?.split(' ');
+    let (width, height) = parts.next()?.split_once('x')?;
+    let bomb_count = parts.next()?;
+    Some((width.parse().ok()?, height.parse().ok()?, bomb_count.parse().ok()?))
+}
+
+// Games in the timed modes are kept separate from normal ones
+pub fn stats_key(difficulty: Difficulty, mode: GameMode) -> String {
+    match mode {
+        GameMode::Classic => difficulty_key(difficulty),
+        _ => format!("{} {}", difficulty_key(difficulty), mode.name()),
+    }
+}
+
+pub fn difficulty_name(difficulty: Difficulty, lang: &Lang) -> String {
+    match difficulty {
+        Difficulty::Easy   => lang.get("difficulty.easy"),
+        Difficulty::Normal => lang.get("difficulty.normal"),
+        Difficulty::Hard   => lang.get("difficulty.hard"),
+        Difficulty::Custom(v) => lang.format("difficulty.custom", &[&v.width(), &v.height(), &v.bomb_count()]),
+    }
+}
+
+// e.g. 83.42 -> "1:23.4"
+pub fn format_time(time: f32) -> String {
+    let tenths = (time * 10.0) as usize;
+    format!("{}:{:02}.{}", tenths / 600, (tenths / 10) % 60, tenths % 10)
+}
+
+// Today's date as "YYYY-MM-DD", in UTC
+pub fn today() -> String {
+    date_string(today_days())
+}
+
+pub fn today_days() -> i64 {
+    (macroquad::miniquad::date::now() / 86400.0).floor() as i64
+}
+
+pub fn date_string(days: i64) -> String {
+    let (y, m, d) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+// Turns a number of days since 1970-01-01 into a (year, month, day), thanks to Howard Hinnant's date algorithms
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
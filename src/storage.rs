@@ -0,0 +1,51 @@
+// Saving and loading little bits of text between launches, such as the settings.
+// On desktop each key is a file in the user's config directory, on the web it's an entry in the browser's local storage.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use std::path::PathBuf;
+
+    fn path(key: &str) -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("minesweeper").join(key))
+    }
+
+    pub fn load(key: &str) -> Option<String> {
+        std::fs::read_to_string(path(key)?).ok()
+    }
+
+    pub fn save(key: &str, data: &str) -> Result<(), String> {
+        let path = path(key).ok_or("no config directory")?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(path, data).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub fn load(key: &str) -> Option<String> {
+        local_storage()?.get_item(&format!("minesweeper_{}", key)).ok()?
+    }
+
+    pub fn save(key: &str, data: &str) -> Result<(), String> {
+        local_storage()
+            .ok_or("no local storage")?
+            .set_item(&format!("minesweeper_{}", key), data)
+            .map_err(|e| format!("{:?}", e))
+    }
+}
+
+pub fn load(key: &str) -> Option<String> {
+    platform::load(key)
+}
+
+pub fn save(key: &str, data: &str) {
+    if let Err(e) = platform::save(key, data) {
+        macroquad::logging::error!("Error '{}' saving '{}'!", e, key);
+    }
+}
@@ -0,0 +1,288 @@
+// Which mouse buttons and keys dig, flag and chord. Each action can have any number of inputs,
+// and they're saved in the settings as the names of the inputs separated by commas.
+
+use macroquad::input::{is_key_down, is_key_pressed, KeyCode, MouseButton};
+
+use crate::lang::Lang;
+
+use super::state::State;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Action {
+    Dig, Flag, Chord,
+}
+
+impl Action {
+    pub const ALL: [Action; 3] = [Action::Dig, Action::Flag, Action::Chord];
+
+    pub fn name(&self) -> &'static str {match self {
+        Action::Dig   => "dig",
+        Action::Flag  => "flag",
+        Action::Chord => "chord",
+    }}
+    pub fn label_key(&self) -> &'static str {match self {
+        Action::Dig   => "popup.settings.dig",
+        Action::Flag  => "popup.settings.flag",
+        Action::Chord => "popup.settings.chord",
+    }}
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Input {
+    Mouse(MouseButton),
+    // Two mouse buttons held down together
+    MouseChord(MouseButton, MouseButton),
+    // A mouse button clicked while holding down a key
+    KeyMouse(KeyCode, MouseButton),
+    Key(KeyCode),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Preset {
+    Default, LeftHanded, OneButton,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Default, Preset::LeftHanded, Preset::OneButton];
+
+    pub fn label_key(&self) -> &'static str {match self {
+        Preset::Default    => "popup.controls.default",
+        Preset::LeftHanded => "popup.controls.left_handed",
+        Preset::OneButton  => "popup.controls.one_button",
+    }}
+}
+
+// The keys that can be bound, the rest are already used for moving around, zooming, shortcuts and the like
+const BINDABLE_KEYS: [KeyCode; 59] = [
+    KeyCode::B, KeyCode::C, KeyCode::E, KeyCode::F, KeyCode::G, KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M,
+    KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::T, KeyCode::U, KeyCode::V, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    KeyCode::Key4, KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::Space, KeyCode::Enter, KeyCode::Backspace, KeyCode::Comma, KeyCode::Period, KeyCode::Slash, KeyCode::Semicolon,
+    KeyCode::Apostrophe, KeyCode::LeftBracket, KeyCode::RightBracket, KeyCode::Backslash, KeyCode::GraveAccent,
+    KeyCode::Insert, KeyCode::Delete, KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown,
+    KeyCode::Kp1, KeyCode::Kp2, KeyCode::Kp3, KeyCode::Kp4, KeyCode::Kp5, KeyCode::Kp6, KeyCode::Kp7, KeyCode::Kp8, KeyCode::Kp9, KeyCode::KpEnter,
+    KeyCode::LeftControl, KeyCode::RightControl, KeyCode::LeftAlt, KeyCode::RightAlt,
+];
+// These only go with a click, on their own they'd go off with every shortcut
+const MODIFIER_KEYS: [KeyCode; 4] = [KeyCode::LeftControl, KeyCode::RightControl, KeyCode::LeftAlt, KeyCode::RightAlt];
+const MOUSE_BUTTONS: [MouseButton; 3] = [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+
+pub fn bindable(key: KeyCode) -> bool {
+    BINDABLE_KEYS.contains(&key)
+}
+
+fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS.into_iter().find(|k| key_name(*k) == name)
+}
+fn mouse_name(button: MouseButton) -> &'static str {match button {
+    MouseButton::Left   => "mouse_left",
+    MouseButton::Right  => "mouse_right",
+    MouseButton::Middle => "mouse_middle",
+    MouseButton::Unknown => "",
+}}
+fn mouse_from_name(name: &str) -> Option<MouseButton> {
+    MOUSE_BUTTONS.into_iter().find(|b| mouse_name(*b) == name)
+}
+
+impl Input {
+    // How it's written in the settings file
+    pub fn name(&self) -> String {
+        match *self {
+            Input::Mouse(b)         => mouse_name(b).to_owned(),
+            Input::MouseChord(a, b) => format!("{}+{}", mouse_name(a), mouse_name(b)),
+            Input::KeyMouse(k, b)   => format!("{}+{}", key_name(k), mouse_name(b)),
+            Input::Key(k)           => key_name(k),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Input> {
+        let parts: Vec<&str> = name.split('+').map(str::trim).collect();
+        match parts[..] {
+            [one] => mouse_from_name(one).map(Input::Mouse).or_else(|| key_from_name(one).filter(|k| !MODIFIER_KEYS.contains(k)).map(Input::Key)),
+            [a, b] => match (mouse_from_name(a), key_from_name(a), mouse_from_name(b)) {
+                (Some(a), _, Some(b)) if a != b => Some(Input::MouseChord(a, b)),
+                (_, Some(k), Some(b))           => Some(Input::KeyMouse(k, b)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // How many buttons and keys it takes, so a chord isn't replaced by one of its buttons as they're let go
+    fn parts(&self) -> usize {
+        match self {
+            Input::Mouse(_) | Input::Key(_) => 1,
+            Input::MouseChord(..) | Input::KeyMouse(..) => 2,
+        }
+    }
+
+    // How it's shown to the player. Key names are always in English, like they are on most keyboards
+    pub fn label(&self, lang: &Lang) -> String {
+        let button = |b: MouseButton| lang.get(match b {
+            MouseButton::Right  => "input.right",
+            MouseButton::Middle => "input.middle",
+            _                   => "input.left",
+        });
+        match *self {
+            Input::Mouse(b)         => lang.format("input.click", &[&button(b)]),
+            Input::MouseChord(a, b) => lang.format("input.click", &[&format!("{}+{}", button(a), button(b))]),
+            Input::KeyMouse(k, b)   => format!("{}+{}", key_name(k), lang.format("input.click", &[&button(b)])),
+            Input::Key(k)           => key_name(k),
+        }
+    }
+}
+
+// Whatever's being held down right now, as an input, for binding things to
+pub fn held_input(state: &State) -> Option<Input> {
+    let key = BINDABLE_KEYS.into_iter().find(|k| is_key_down(*k));
+    let buttons: Vec<MouseButton> = MOUSE_BUTTONS.into_iter().filter(|b| state.mouse_down(*b)).collect();
+    match (key, &buttons[..]) {
+        (_,       [a, b, ..]) => Some(Input::MouseChord(*a, *b)),
+        (Some(k), [b])        => Some(Input::KeyMouse(k, *b)),
+        (None,    [b])        => Some(Input::Mouse(*b)),
+        (Some(k), []) if !MODIFIER_KEYS.contains(&k) => Some(Input::Key(k)),
+        (_,       [])         => None,
+    }
+}
+
+// Keeps track of what's held down while waiting for something to be bound,
+// returning the biggest input that was held once everything's been let go
+pub fn listen(held: &mut Option<Input>, state: &State) -> Option<Input> {
+    match (held_input(state), *held) {
+        (Some(input), Some(h)) if input.parts() < h.parts() => None,
+        (Some(input), _) => { *held = Some(input); None }
+        (None, _) => held.take(),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Bindings {
+    bindings: Vec<(Input, Action)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings::preset(Preset::Default)
+    }
+}
+
+impl Bindings {
+    pub fn preset(preset: Preset) -> Bindings {
+        use {Action::*, Input::*, MouseButton::{Left, Middle, Right}};
+        let mouse = match preset {
+            Preset::Default    => vec![(Mouse(Left),  Dig), (Mouse(Right), Flag), (Mouse(Middle), Chord), (MouseChord(Left, Right), Chord)],
+            Preset::LeftHanded => vec![(Mouse(Right), Dig), (Mouse(Left),  Flag), (Mouse(Middle), Chord), (MouseChord(Left, Right), Chord)],
+            // For trackpads and mice with just the one button
+            Preset::OneButton  => vec![(Mouse(Left),  Dig), (KeyMouse(KeyCode::LeftControl, Left), Flag), (KeyMouse(KeyCode::LeftAlt, Left), Chord)],
+        };
+        let keys = [(Key(KeyCode::Space), Dig), (Key(KeyCode::Enter), Dig), (Key(KeyCode::F), Flag), (Key(KeyCode::C), Chord)];
+        Bindings { bindings: mouse.into_iter().chain(keys).collect() }
+    }
+
+    pub fn inputs(&self, action: Action) -> impl Iterator<Item = Input> + '_ {
+        self.bindings.iter().filter(move |(_, a)| *a == action).map(|(i, _)| *i)
+    }
+
+    // An input only ever does one thing, so binding it takes it away from whatever it did before
+    pub fn bind(&mut self, input: Input, action: Action) {
+        self.bindings.retain(|(i, _)| *i != input);
+        self.bindings.push((input, action));
+    }
+    pub fn clear(&mut self, action: Action) {
+        self.bindings.retain(|(_, a)| *a != action);
+    }
+
+    // Whether one of the action's mouse inputs is down, or was pressed or released, this frame
+    pub fn mouse_down(&self, action: Action, state: &State) -> bool {
+        self.mouse(action, state, State::mouse_down)
+    }
+    pub fn mouse_pressed(&self, action: Action, state: &State) -> bool {
+        self.mouse(action, state, State::mouse_pressed)
+    }
+    pub fn mouse_released(&self, action: Action, state: &State) -> bool {
+        self.mouse(action, state, State::mouse_released)
+    }
+
+    fn mouse(&self, action: Action, state: &State, check: fn(&State, MouseButton) -> bool) -> bool {
+        self.inputs(action).any(|input| match input {
+            // A button on its own doesn't count when it's being used with a key for something else
+            Input::Mouse(b)         => check(state, b) && !self.key_held_with(b),
+            Input::MouseChord(a, b) => state.mouse_down(a) && state.mouse_down(b) && (check(state, a) || check(state, b)),
+            Input::KeyMouse(k, b)   => is_key_down(k) && check(state, b),
+            Input::Key(_)           => false,
+        })
+    }
+
+    fn key_held_with(&self, button: MouseButton) -> bool {
+        self.bindings.iter().any(|(i, _)| matches!(*i, Input::KeyMouse(k, b) if b == button && is_key_down(k)))
+    }
+
+    pub fn key_pressed(&self, action: Action) -> bool {
+        self.inputs(action).any(|input| matches!(input, Input::Key(k) if is_key_pressed(k)))
+    }
+
+    // Touches don't care about the mouse bindings, a tap digs and a long press flags, or the other way round in flag mode
+    pub fn touch(flag_mode: bool) -> Bindings {
+        let (dig, flag) = match flag_mode {
+            false => (MouseButton::Left,  MouseButton::Right),
+            true  => (MouseButton::Right, MouseButton::Left),
+        };
+        Bindings { bindings: vec![(Input::Mouse(dig), Action::Dig), (Input::Mouse(flag), Action::Flag)] }
+    }
+
+    // Each action's inputs, as they're written in the settings file
+    pub fn to_names(&self, action: Action) -> String {
+        self.inputs(action).map(|i| i.name()).collect::<Vec<_>>().join(", ")
+    }
+    // Replaces an action's inputs with the ones in a line from the settings file, leaving out any that don't make sense
+    pub fn set_from_names(&mut self, action: Action, names: &str) {
+        self.clear(action);
+        for input in names.split(',').filter_map(|n| Input::from_name(n.trim())) {
+            self.bind(input, action);
+        }
+    }
+
+    // Every input that does something, for the settings popup
+    pub fn label(&self, action: Action, lang: &Lang) -> String {
+        match self.inputs(action).next() {
+            None => lang.get("popup.controls.nothing"),
+            Some(_) => self.inputs(action).map(|i| i.label(lang)).collect::<Vec<_>>().join(", "),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(bindings: &Bindings, action: Action) -> Vec<Input> {
+        bindings.inputs(action).collect()
+    }
+
+    #[test]
+    fn names_round_trip() {
+        let mut custom = Bindings::preset(Preset::OneButton);
+        custom.bind(Input::MouseChord(MouseButton::Right, MouseButton::Middle), Action::Flag);
+        custom.bind(Input::Key(KeyCode::Q), Action::Chord);
+
+        for bindings in Preset::ALL.map(Bindings::preset).into_iter().chain([custom]) {
+            let mut read = Bindings { bindings: Vec::new() };
+            for action in Action::ALL {
+                read.set_from_names(action, &bindings.to_names(action));
+            }
+            for action in Action::ALL {
+                assert_eq!(inputs(&read, action), inputs(&bindings, action));
+            }
+        }
+    }
+
+    #[test]
+    fn nonsense_names_are_left_out() {
+        let mut bindings = Bindings::default();
+        bindings.set_from_names(Action::Dig, "mouse_left, wiggle, mouse_left+mouse_left, LeftShift, mouse_right+mouse_middle+mouse_left");
+        assert_eq!(inputs(&bindings, Action::Dig), [Input::Mouse(MouseButton::Left)]);
+    }
+}
@@ -1,6 +1,6 @@
-use macroquad::{color::Color, input::{clear_input_queue, get_char_pressed, get_last_key_pressed, KeyCode}, math::{vec2, Rect}};
+use macroquad::{color::Color, input::{clear_input_queue, get_char_pressed, get_last_key_pressed, is_key_down, is_key_pressed, KeyCode, MouseButton}, math::{vec2, Rect}, miniquad::window::{clipboard_get, clipboard_set}};
 
-use super::{renderer::{text_renderer::Caret, DrawShape, Renderer}, state::{ButtonState, Id, State}};
+use super::{renderer::{sound::SoundEffect, style::SHADOW, text_renderer::Caret, DrawShape, Renderer}, state::{ButtonState, Id, State}};
 
 #[derive(Clone, Copy)]
 pub enum Align {
@@ -41,7 +41,33 @@ pub fn centered_rect(x: f32, y: f32, w: f32, h: f32) -> Rect {
 pub fn text(text: String, line_gap: Option<f32>, color: Color, x: Align, y: Align, renderer: &mut Renderer) {
     let size = renderer.text_renderer.text_size(&text, line_gap);
     let rect = aligned_rect(x, y, size.x, size.y);
-    renderer.draw(DrawShape::text(rect.x, rect.y, text, line_gap, None, None, color));
+    renderer.draw(DrawShape::text(rect.x, rect.y, text, line_gap, None, color));
+}
+
+// A ring around whichever widget's been tabbed to
+fn focus_ring(id: Id, rect: Rect, state: &State, renderer: &mut Renderer) {
+    if !state.focus_ring(id) {
+        return;
+    }
+    let (r, color) = (Rect::new(rect.x - 1.0, rect.y - 1.0, rect.w + 2.0, rect.h + 2.0), renderer.style().focus_ring());
+    for side in [
+        Rect::new(r.x,             r.y,              r.w, 1.0),
+        Rect::new(r.x,             r.bottom() - 1.0, r.w, 1.0),
+        Rect::new(r.x,             r.y,              1.0, r.h),
+        Rect::new(r.right() - 1.0, r.y,              1.0, r.h),
+    ] {
+        renderer.draw(DrawShape::rect(side, color));
+    }
+}
+
+// The state of a button that can also be pressed with the keyboard when it's focused
+fn focusable_button_state(id: Id, rect: Rect, disabled: bool, held_when_not_hovered: bool, state: &mut State) -> ButtonState {
+    let button_state = state.button_state(id, state.mouse_in_rect(rect), disabled, held_when_not_hovered);
+    state.focusable(id, &button_state, !disabled);
+    match !disabled && state.focus_pressed(id) {
+        true  => ButtonState::Released,
+        false => button_state,
+    }
 }
 
 pub fn url(id: Id, text: String, url: String, line_gap: Option<f32>, x: Align, y: Align, state: &mut State, renderer: &mut Renderer) {
@@ -49,17 +75,22 @@ pub fn url(id: Id, text: String, url: String, line_gap: Option<f32>, x: Align, y
     let rect = aligned_rect(x, y, size.x, size.y);
     let underline = Rect::new(rect.x, rect.y + rect.h - 1.0, rect.w, 1.0);
     
-    renderer.draw(DrawShape::text(rect.x, rect.y, text, line_gap, None, None, renderer.style().text_url()));
+    focus_ring(id, rect, state, renderer);
+    renderer.draw(DrawShape::text(rect.x, rect.y, text, line_gap, None, renderer.style().text_url()));
     renderer.draw(DrawShape::rect(underline, renderer.style().text_url()));
 
-    if state.button_state(id, state.mouse_in_rect(rect), false, false).released() {
+    if focusable_button_state(id, rect, false, false, state).released() {
         let _ = webbrowser::open(&url);
     }
 }
 
 pub fn button(id: Id, x: Align, y: Align, w: f32, h: f32, disabled: bool, state: &mut State, renderer: &mut Renderer) -> ButtonState {
     let rect = aligned_rect(x, y, w, h);
-    let button_state = state.button_state(id, state.mouse_in_rect(rect), disabled, true);
+    let button_state = focusable_button_state(id, rect, disabled, true, state);
+    if button_state.released() {
+        renderer.sound_player_mut().play(SoundEffect::Click);
+    }
+    focus_ring(id, rect, state, renderer);
 
     let (offset, source, _) = renderer.style().button(&button_state);
     renderer.draw(DrawShape::nineslice(rect.offset(offset), source));
@@ -70,70 +101,391 @@ pub fn button(id: Id, x: Align, y: Align, w: f32, h: f32, disabled: bool, state:
 pub fn button_text(id: Id, text: String, x: Align, y: Align, disabled: bool, state: &mut State, renderer: &mut Renderer) -> ButtonState {
     let button_size = renderer.text_renderer.text_size(&text, None) + vec2(6.0, 4.0);
     let rect = aligned_rect(x, y, button_size.x, button_size.y);
-    let button_state = state.button_state(id, state.mouse_in_rect(rect), disabled, true);
+    let button_state = focusable_button_state(id, rect, disabled, true, state);
+    if button_state.released() {
+        renderer.sound_player_mut().play(SoundEffect::Click);
+    }
+    focus_ring(id, rect, state, renderer);
 
     let (offset, source, text_col) = renderer.style().button(&button_state);
 
     let rect = rect.offset(offset);
-    renderer.draw(DrawShape::text(rect.x + 3.0 , rect.y + 2.0, text, None, None, None, text_col));
+    renderer.draw(DrawShape::text(rect.x + 3.0 , rect.y + 2.0, text, None, None, text_col));
     renderer.draw(DrawShape::nineslice(rect, source));
 
     button_state
 }
 
-pub fn number_field(id: Id, number: &mut String, max: usize, hint: String, x: Align, y: Align, w: f32, state: &mut State, renderer: &mut Renderer) {
+// A little box with a tick in it, clicking it toggles the value
+pub fn checkbox(id: Id, value: &mut bool, x: Align, y: Align, state: &mut State, renderer: &mut Renderer) -> bool {
+    let rect = aligned_rect(x, y, 7.0, 7.0);
+    let button_state = focusable_button_state(id, rect, false, false, state);
+    if button_state.released() {
+        *value = !*value;
+        renderer.sound_player_mut().play(SoundEffect::Click);
+    }
+    focus_ring(id, rect, state, renderer);
+
+    if *value {
+        renderer.draw(DrawShape::rect(Rect::new(rect.x + 2.0, rect.y + 2.0, 3.0, 3.0), renderer.style().text()));
+    }
+    renderer.draw(DrawShape::nineslice(rect, renderer.style().text_input()));
+    button_state.released()
+}
+
+// A thumb that's dragged along a track, snapping to every step between min and max.
+// When it's focused the left and right keys move it a step at a time
+#[allow(clippy::too_many_arguments)]
+pub fn slider(id: Id, value: &mut f32, min: f32, max: f32, step: f32, x: Align, y: Align, w: f32, state: &mut State, renderer: &mut Renderer) -> bool {
+    const THUMB_WIDTH: f32 = 5.0;
+    let rect = aligned_rect(x, y, w, 7.0);
+    let button_state = state.button_state(id, state.mouse_in_rect(rect), false, true);
+    let focused = state.focusable(id, &button_state, false);
+
+    let old_value = *value;
+    if button_state.clicked() {
+        state.slider_start = *value;
+    }
+    // Dragging the thumb, which can carry on outside of the track
+    if matches!(button_state, ButtonState::Clicked | ButtonState::Held | ButtonState::Released) {
+        let along = ((state.mouse_pos().x - rect.x - THUMB_WIDTH / 2.0) / (rect.w - THUMB_WIDTH)).clamp(0.0, 1.0);
+        *value = (min + ((max - min) * along / step).round() * step).clamp(min, max);
+    }
+    let key_step = match focused { true => arrow_keys(KeyCode::Left, KeyCode::Right, state), false => 0 };
+    if key_step != 0 {
+        // Rounded so the steps don't drift away from where the mouse would put them
+        *value = (min + ((*value - min) / step).round() * step + key_step as f32 * step).clamp(min, max);
+    }
+    // Clicking once it's let go of, if it ended up somewhere different to where it started
+    if (button_state.released() && *value != state.slider_start) || (key_step != 0 && *value != old_value) {
+        renderer.sound_player_mut().play(SoundEffect::Click);
+    }
+    focus_ring(id, rect, state, renderer);
+
+    let along = ((*value - min) / (max - min)).clamp(0.0, 1.0);
+    let thumb = Rect::new((rect.x + along * (rect.w - THUMB_WIDTH)).round(), rect.y, THUMB_WIDTH, rect.h);
+    let (offset, source, _) = renderer.style().button(&button_state);
+    renderer.draw(DrawShape::nineslice(thumb.offset(offset), source));
+    renderer.draw(DrawShape::nineslice(Rect::new(rect.x, rect.y + 2.0, rect.w, 3.0), renderer.style().text_input()));
+    *value != old_value
+}
+
+// A button showing the selected option, which opens a list of all of them underneath it.
+// The list's drawn on top of anything that comes after it, so selects should go above whatever they might cover.
+// When it's focused the up and down keys pick the option before or after, and escape closes the list
+#[allow(clippy::too_many_arguments)]
+pub fn select(id: Id, options: &[String], selected: &mut usize, x: Align, y: Align, w: f32, state: &mut State, renderer: &mut Renderer) -> bool {
+    let row_height = renderer.text_renderer.line_gap(None) + 2.0;
+    let rect = aligned_rect(x, y, w, row_height);
+    let list_rect = Rect::new(rect.x, rect.bottom(), rect.w, options.len() as f32 * row_height + 2.0);
+
+    if state.select == Some(id) {
+        state.select_drawn = true;
+        // Clicking anywhere else closes it
+        if (state.mouse_pressed(MouseButton::Left) && !state.mouse_in_rect(rect) && !state.mouse_in_rect(list_rect)) || state.escape_pressed() {
+            state.select = None;
+        }
+    }
+
+    let old_selected = *selected;
+    if state.focus == Some(id) && !options.is_empty() {
+        *selected = (*selected as isize + arrow_keys(KeyCode::Up, KeyCode::Down, state)).clamp(0, options.len() as isize - 1) as usize;
+    }
+    if state.select == Some(id) {
+        for (i, option) in options.iter().enumerate() {
+            let row = Rect::new(list_rect.x + 1.0, list_rect.y + 1.0 + i as f32 * row_height, list_rect.w - 2.0, row_height);
+            let option_id = super::hash_string(&format!("{}option{}", id, i));
+            let option_state = state.button_state(option_id, state.mouse_in_rect(row), false, false);
+            if option_state.released() {
+                *selected = i;
+                state.select = None;
+                renderer.sound_player_mut().play(SoundEffect::Click);
+            }
+            let (background, text_col, _) = renderer.style().menubar(option_state != ButtonState::Idle);
+            renderer.draw(DrawShape::text(row.x + 2.0, row.y + 2.0, option.clone(), None, None, text_col));
+            renderer.draw(DrawShape::rect(row, background));
+        }
+        renderer.draw(DrawShape::nineslice(list_rect, renderer.style().dropdown_background()));
+        renderer.draw(DrawShape::rect(list_rect.offset(vec2(3.0, 3.0)), SHADOW));
+        // Nothing underneath the list should be clickable
+        state.hot_item.make_unavailable_if_none_and(state.mouse_in_rect(list_rect));
+    }
+
+    let button_state = focusable_button_state(id, rect, false, true, state);
+    if button_state.released() {
+        state.select = match state.select == Some(id) {
+            true  => None,
+            false => Some(id),
+        };
+        renderer.sound_player_mut().play(SoundEffect::Click);
+    }
+
+    focus_ring(id, rect, state, renderer);
+    let (offset, source, text_col) = renderer.style().button(&button_state);
+    let rect = rect.offset(offset);
+    let text = options.get(*selected).cloned().unwrap_or_default();
+    renderer.draw(DrawShape::text(rect.x + 3.0, rect.y + 2.0, text, None, None, text_col));
+    // A little arrow pointing down
+    for (i, w) in [5.0, 3.0, 1.0].into_iter().enumerate() {
+        renderer.draw(DrawShape::rect(Rect::new(rect.right() - 5.0 - w / 2.0, rect.y + 3.0 + i as f32, w, 1.0), text_col));
+    }
+    renderer.draw(DrawShape::nineslice(rect, source));
+    *selected != old_selected
+}
+
+// A row of buttons that switch between pages, with the current one held down.
+// The whole row's focused at once, and the left and right keys switch to the page before or after
+pub fn tabs(id: Id, labels: &[String], selected: &mut usize, x: Align, y: Align, state: &mut State, renderer: &mut Renderer) -> bool {
+    let sizes: Vec<_> = labels.iter().map(|l| renderer.text_renderer.text_size(l, None) + vec2(6.0, 4.0)).collect();
+    let width = sizes.iter().map(|s| s.x).sum::<f32>();
+    let height = sizes.iter().map(|s| s.y).fold(0.0, f32::max);
+    let rect = aligned_rect(x, y, width, height);
+
+    let old_selected = *selected;
+    let clicked = match state.mouse_in_rect(rect) && state.mouse_pressed(MouseButton::Left) && state.hot_item.is_none() {
+        true  => ButtonState::Clicked,
+        false => ButtonState::Idle,
+    };
+    if state.focusable(id, &clicked, false) && !labels.is_empty() {
+        *selected = (*selected as isize + arrow_keys(KeyCode::Left, KeyCode::Right, state)).clamp(0, labels.len() as isize - 1) as usize;
+        if *selected != old_selected {
+            renderer.sound_player_mut().play(SoundEffect::Click);
+        }
+    }
+    focus_ring(id, rect, state, renderer);
+    let mut tab_x = rect.x;
+    for (i, (label, size)) in labels.iter().zip(sizes).enumerate() {
+        let tab = Rect::new(tab_x, rect.y, size.x, height);
+        tab_x += size.x;
+        let tab_id = super::hash_string(&format!("{}tab{}", id, i));
+        let mut button_state = state.button_state(tab_id, state.mouse_in_rect(tab), false, false);
+        if button_state.released() && *selected != i {
+            *selected = i;
+            renderer.sound_player_mut().play(SoundEffect::Click);
+        }
+        if *selected == i {
+            button_state = ButtonState::Held;
+        }
+        let (offset, source, text_col) = renderer.style().button(&button_state);
+        let tab = tab.offset(offset);
+        renderer.draw(DrawShape::text(tab.x + 3.0, tab.y + 2.0, label.clone(), None, None, text_col));
+        renderer.draw(DrawShape::nineslice(tab, source));
+    }
+    *selected != old_selected
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn number_field(id: Id, number: &mut String, max: usize, hint: String, x: Align, y: Align, w: f32, state: &mut State, renderer: &mut Renderer) -> bool {
+    text_field(id, number, max, hint, |n| n.chars().all(|c| c.is_ascii_digit()), x, y, w, state, renderer)
+}
+
+// A box that can have any text typed into it, with the usual ways of moving around, selecting and copying and pasting.
+// Anything that'd make it longer than max chars or that valid doesn't like is ignored
+#[allow(clippy::too_many_arguments)]
+pub fn text_field(id: Id, text: &mut String, max: usize, hint: String, valid: fn(&str) -> bool, x: Align, y: Align, w: f32, state: &mut State, renderer: &mut Renderer) -> bool {
     let h = renderer.text_renderer.line_gap(None) + 2.0;
     let rect = aligned_rect(x, y, w, h);
+    let inner_width = rect.w - 4.0;
+    let old_text = text.clone();
 
-    let button_state = state.button_state(id, state.mouse_in_rect(rect), false, false);
+    let button_state = state.button_state(id, state.mouse_in_rect(rect), false, true);
+    // Tabbing to it starts typing into it, with everything selected so it can be typed over
+    if state.focusable(id, &button_state, false) && state.text_field != Some(id) && !button_state.clicked() {
+        state.text_field = Some(id);
+        (state.selection, state.caret) = (0, text.chars().count());
+        state.text_scroll = 0;
+        clear_input_queue();
+    }
 
-    if state.text_field.is_some_and(|i| i == id) {
-        if let Some(click_pos) = renderer.text_click_pos {
-            state.caret = click_pos;
+    if state.text_field == Some(id) {
+        state.text_field_drawn = true;
+        // Clicking anywhere else unfocuses it
+        if state.mouse_pressed(MouseButton::Left) && !state.mouse_in_rect(rect) {
+            state.text_field = None;
         }
-        state.caret = state.caret.min(number.len());
+    }
+    if button_state.clicked() && state.text_field != Some(id) {
+        state.text_field = Some(id);
+        state.text_scroll = 0;
+        clear_input_queue();
+    }
 
-        let mut reset_flash = 0;
-        match get_char_pressed() {
-            Some(c) if c.is_digit(10) && number.len() < max => { number.insert(state.caret, c); state.caret += 1; }
-            _ => reset_flash += 1,
-        }
+    let focused = state.text_field == Some(id);
+    if focused {
+        let len = text.chars().count();
+        state.caret     = state.caret.min(len);
+        state.selection = state.selection.min(len);
 
-        match get_last_key_pressed().or_else(|| state.key_held()) {
-            // Left
-            Some(KeyCode::Left)  => state.caret = state.caret.saturating_sub(1).min(number.len()),
-            // Right
-            Some(KeyCode::Right) => state.caret = state.caret.saturating_add(1).min(number.len()),
-            // Backspace
-            Some(KeyCode::Backspace) if state.caret > 0 => {state.caret -= 1; number.remove(state.caret);}
-            // Delete
-            Some(KeyCode::Delete) if state.caret < number.len() => { number.remove(state.caret); }
-            _ => reset_flash += 1,
+        // Clicking puts the caret under the mouse and dragging selects, shift+clicking selects from wherever the caret was
+        if matches!(button_state, ButtonState::Clicked | ButtonState::Held) {
+            let scrolled: String = text.chars().skip(state.text_scroll).collect();
+            state.caret = state.text_scroll + renderer.text_renderer.caret_index(&scrolled, state.mouse_pos().x - rect.x - 2.0);
+            if button_state.clicked() && !is_shift_down() {
+                state.selection = state.caret;
+            }
+            renderer.caret_timer = 0.0;
         }
-        if reset_flash != 2 {
+        if edit_text(text, max, valid, state) {
             renderer.caret_timer = 0.0;
         }
+
+        // Scrolling so the caret's always in view
+        state.text_scroll = state.text_scroll.min(state.caret);
+        while renderer.text_renderer.caret_x(&text.chars().skip(state.text_scroll).collect::<String>(), state.caret - state.text_scroll) > inner_width {
+            state.text_scroll += 1;
+        }
     }
 
-    let caret = match state.text_field {
-        Some(i) if i == id => Some(Caret { index: state.caret, color: renderer.style().text() }),
-        _ => None
-    };
+    // Only the bit that fits in the box is drawn
+    let scroll = if focused { state.text_scroll } else { 0 };
+    let mut shown: String = text.chars().skip(scroll).collect();
+    while renderer.text_renderer.text_size(&shown, None).x > inner_width {
+        shown.pop();
+    }
+    let shown_len = shown.chars().count();
 
-    let click_pos = match button_state {
-        ButtonState::Clicked => {
-            state.text_field = Some(id);
-            clear_input_queue();
-            Some(state.mouse_pos())
-        },
-        _ => None,
-    };
+    let (start, end) = (state.caret.min(state.selection), state.caret.max(state.selection));
+    if focused && start != end {
+        let (start, end) = (start.clamp(scroll, scroll + shown_len) - scroll, end.clamp(scroll, scroll + shown_len) - scroll);
+        let (start_x, end_x) = (renderer.text_renderer.caret_x(&shown, start), renderer.text_renderer.caret_x(&shown, end));
+        let (background, text_col, _) = renderer.style().menubar(true);
+        let selected: String = shown.chars().skip(start).take(end - start).collect();
+        renderer.draw(DrawShape::text(rect.x + 2.0 + start_x, rect.y + 2.0, selected, None, None, text_col));
+        renderer.draw(DrawShape::rect(Rect::new(rect.x + 2.0 + start_x, rect.y + 1.0, end_x - start_x, rect.h - 2.0), background));
+    }
 
-    let text = match number.is_empty() {
-        true  => DrawShape::text(rect.x + 2.0, rect.y + 2.0, hint,           None, caret, click_pos, renderer.style().text_disabled()),
-        false => DrawShape::text(rect.x + 2.0, rect.y + 2.0, number.clone(), None, caret, click_pos, renderer.style().text()),
+    let caret = match focused {
+        true  => Some(Caret { index: state.caret - scroll, color: renderer.style().text() }),
+        false => None,
+    };
+    let text_shape = match text.is_empty() {
+        true  => DrawShape::text(rect.x + 2.0, rect.y + 2.0, hint,  None, caret, renderer.style().text_disabled()),
+        false => DrawShape::text(rect.x + 2.0, rect.y + 2.0, shown, None, caret, renderer.style().text()),
     };
 
-    renderer.draw(text);
+    focus_ring(id, rect, state, renderer);
+    renderer.draw(text_shape);
     renderer.draw(DrawShape::nineslice(rect, renderer.style().text_input()));
-}
\ No newline at end of file
+    *text != old_text
+}
+
+// -1 if the key for going back was pressed (or held down), 1 for going forwards
+fn arrow_keys(back: KeyCode, forward: KeyCode, state: &mut State) -> isize {
+    let held = state.key_held();
+    let pressed = |key: KeyCode| is_key_pressed(key) || held == Some(key);
+    pressed(forward) as isize - pressed(back) as isize
+}
+
+fn is_shift_down() -> bool {
+    is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift)
+}
+// Command on macs
+pub fn is_ctrl_down() -> bool {
+    [KeyCode::LeftControl, KeyCode::RightControl, KeyCode::LeftSuper, KeyCode::RightSuper].iter().any(|k| is_key_down(*k))
+}
+
+// Where the word before the caret starts, and where the one after it ends, for jumping and deleting a word at a time
+fn word_start(chars: &[char], mut i: usize) -> usize {
+    while i > 0 && chars[i - 1].is_whitespace()  { i -= 1; }
+    while i > 0 && !chars[i - 1].is_whitespace() { i -= 1; }
+    i
+}
+fn word_end(chars: &[char], mut i: usize) -> usize {
+    while i < chars.len() && chars[i].is_whitespace()  { i += 1; }
+    while i < chars.len() && !chars[i].is_whitespace() { i += 1; }
+    i
+}
+
+// Puts some text in place of a range of chars, leaving the caret after it. Returns false if the result isn't allowed
+fn replace_range(chars: &mut Vec<char>, range: std::ops::Range<usize>, with: &str, max: usize, valid: fn(&str) -> bool, state: &mut State) -> bool {
+    let mut new_chars = chars.clone();
+    new_chars.splice(range.clone(), with.chars());
+    if new_chars.len() > max || !valid(&new_chars.iter().collect::<String>()) {
+        return false;
+    }
+    *chars = new_chars;
+    state.caret = range.start + with.chars().count();
+    state.selection = state.caret;
+    true
+}
+
+// Handles the keyboard for the focused text field, returning whether anything was pressed
+fn edit_text(text: &mut String, max: usize, valid: fn(&str) -> bool, state: &mut State) -> bool {
+    // AltGr counts as ctrl too on some systems, but it's for typing, not shortcuts
+    let (ctrl, shift) = (is_ctrl_down() && !is_key_down(KeyCode::RightAlt), is_shift_down());
+    let mut chars: Vec<char> = text.chars().collect();
+    let selection = state.caret.min(state.selection)..state.caret.max(state.selection);
+    let mut pressed = false;
+
+    // Typing replaces whatever's selected
+    while let Some(c) = get_char_pressed() {
+        if !c.is_control() && !ctrl {
+            let selection = state.caret.min(state.selection)..state.caret.max(state.selection);
+            replace_range(&mut chars, selection, &c.to_string(), max, valid, state);
+            pressed = true;
+        }
+    }
+
+    // Select all, copy, cut and paste
+    if ctrl {
+        if is_key_pressed(KeyCode::A) {
+            (state.selection, state.caret) = (0, chars.len());
+            pressed = true;
+        }
+        if (is_key_pressed(KeyCode::C) || is_key_pressed(KeyCode::X)) && !selection.is_empty() {
+            clipboard_set(&chars[selection.clone()].iter().collect::<String>());
+            pressed = true;
+        }
+        if is_key_pressed(KeyCode::X) {
+            replace_range(&mut chars, selection.clone(), "", max, valid, state);
+        }
+        if is_key_pressed(KeyCode::V) {
+            // There's only one line, so newlines and tabs and the like are left out
+            let pasted: String = clipboard_get().unwrap_or_default().chars().filter(|c| !c.is_control()).collect();
+            replace_range(&mut chars, selection.clone(), &pasted, max, valid, state);
+            pressed = true;
+        }
+    }
+
+    // Moving the caret, which selects when holding shift
+    let move_to = |to: usize, state: &mut State| {
+        state.caret = to;
+        if !shift {
+            state.selection = to;
+        }
+    };
+    match get_last_key_pressed().or_else(|| state.key_held()) {
+        // Without shift, moving collapses the selection to whichever end is that way
+        Some(KeyCode::Left)  if !shift && !ctrl && !selection.is_empty() => move_to(selection.start, state),
+        Some(KeyCode::Right) if !shift && !ctrl && !selection.is_empty() => move_to(selection.end,   state),
+        Some(KeyCode::Left)  if ctrl => move_to(word_start(&chars, state.caret), state),
+        Some(KeyCode::Right) if ctrl => move_to(word_end(&chars, state.caret),   state),
+        Some(KeyCode::Left)  => move_to(state.caret.saturating_sub(1), state),
+        Some(KeyCode::Right) => move_to((state.caret + 1).min(chars.len()), state),
+        Some(KeyCode::Home)  => move_to(0, state),
+        Some(KeyCode::End)   => move_to(chars.len(), state),
+        // Deleting takes out the selection, or the char (or word, with ctrl) next to the caret
+        Some(KeyCode::Backspace) => {
+            let range = match (selection.is_empty(), ctrl) {
+                (false, _)    => selection,
+                (true, true)  => word_start(&chars, state.caret)..state.caret,
+                (true, false) => state.caret.saturating_sub(1)..state.caret,
+            };
+            replace_range(&mut chars, range, "", max, valid, state);
+        }
+        Some(KeyCode::Delete) => {
+            let range = match (selection.is_empty(), ctrl) {
+                (false, _)    => selection,
+                (true, true)  => state.caret..word_end(&chars, state.caret),
+                (true, false) => state.caret..(state.caret + 1).min(chars.len()),
+            };
+            replace_range(&mut chars, range, "", max, valid, state);
+        }
+        _ => {
+            *text = chars.into_iter().collect();
+            return pressed;
+        }
+    }
+    *text = chars.into_iter().collect();
+    true
+}
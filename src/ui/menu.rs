@@ -0,0 +1,223 @@
+// What's in the menubar, as data so it can be looked through without being drawn, like for finding what a shortcut does.
+// It's built again every frame from how things are, then Menubar::show draws it and says what was picked
+
+use crate::{lang::Lang, minesweeper::Difficulty};
+
+use super::{minesweeper_element::GameMode, renderer::{sound::{SoundCategory, Volume}, style::FaceType}, shortcuts::Shortcut, Ui};
+
+// Everything the menus can do, which gets done in main
+#[derive(Debug, PartialEq, Clone)]
+pub enum MenuAction {
+    NewGame, Undo, Difficulty(Difficulty), Custom, Daily, Mode(GameMode),
+    AutoFlag, AutoOpen, Statistics, ScreenShake, Settings, Controls, Exit,
+    Hint, About, Skin(String), NumberCues, Face(FaceType), Language(String),
+    AutoScale, Scale(f32), Fit,
+    Mute, Volume(Option<SoundCategory>),
+}
+
+pub enum Label {
+    Key(String),
+    // A key that might not be there, like the names of skins that aren't built in
+    KeyOr(String, String),
+    // Written the same in every language
+    Text(String),
+}
+
+impl Label {
+    pub fn key(key: &str) -> Label {
+        Label::Key(key.to_owned())
+    }
+
+    pub fn text(&self, lang: &Lang) -> String {match self {
+        Label::Key(key)         => lang.get(key),
+        Label::KeyOr(key, text) => lang.try_get(key).unwrap_or(text).to_owned(),
+        Label::Text(text)       => text.clone(),
+    }}
+}
+
+pub struct Item {
+    pub label: Label,
+    pub action: MenuAction,
+    // Radios and toggles have a square next to them when they're picked or on
+    pub checked: bool,
+    // Items that stay open can be clicked over and over, like for stepping through values
+    pub stays_open: bool,
+    // Written on the right, like a board's size or a volume
+    pub other_text: Option<String>,
+    pub shortcut: Option<Shortcut>,
+    // Greyed out and can't be picked, like undo when there's nothing to undo
+    pub disabled: bool,
+}
+
+impl Item {
+    // The other text and then the shortcut
+    pub fn right_text(&self) -> Option<String> {
+        match (&self.other_text, self.shortcut) {
+            (Some(text), Some(shortcut)) => Some(format!("{}  {}", text, shortcut.label())),
+            (Some(text), None)           => Some(text.clone()),
+            (None, Some(shortcut))       => Some(shortcut.label()),
+            (None, None)                 => None,
+        }
+    }
+}
+
+pub enum MenuItem {
+    Item(Item),
+    Separator,
+    Submenu(Label, Vec<MenuItem>),
+}
+
+impl MenuItem {
+    pub fn button(label: Label, action: MenuAction) -> MenuItem {
+        MenuItem::Item(Item { label, action, checked: false, stays_open: false, other_text: None, shortcut: None, disabled: false })
+    }
+    pub fn check(label: Label, checked: bool, action: MenuAction) -> MenuItem {
+        MenuItem::Item(Item { label, action, checked, stays_open: false, other_text: None, shortcut: None, disabled: false })
+    }
+    pub fn cycle(label: Label, value: String, action: MenuAction) -> MenuItem {
+        MenuItem::Item(Item { label, action, checked: false, stays_open: true, other_text: Some(value), shortcut: None, disabled: false })
+    }
+
+    pub fn with_other_text(mut self, text: String) -> MenuItem {
+        if let MenuItem::Item(item) = &mut self {
+            item.other_text = Some(text);
+        }
+        self
+    }
+    pub fn with_shortcut(mut self, shortcut: Shortcut) -> MenuItem {
+        if let MenuItem::Item(item) = &mut self {
+            item.shortcut = Some(shortcut);
+        }
+        self
+    }
+    pub fn disabled(mut self, disabled: bool) -> MenuItem {
+        if let MenuItem::Item(item) = &mut self {
+            item.disabled = disabled;
+        }
+        self
+    }
+}
+
+pub struct Menu {
+    pub key: &'static str,
+    pub items: Vec<MenuItem>,
+}
+
+impl Menu {
+    pub fn label(&self, lang: &Lang) -> String {
+        lang.get(self.key)
+    }
+}
+
+// Every item in the menus, including the ones in submenus
+pub fn items(menus: &[Menu]) -> Vec<&Item> {
+    fn add<'a>(items: &'a [MenuItem], all: &mut Vec<&'a Item>) {
+        for item in items {
+            match item {
+                MenuItem::Item(item)        => all.push(item),
+                MenuItem::Submenu(_, items) => add(items, all),
+                MenuItem::Separator         => (),
+            }
+        }
+    }
+    let mut all = Vec::new();
+    for menu in menus {
+        add(&menu.items, &mut all);
+    }
+    all
+}
+
+// What a shortcut does is whatever the item it's written next to does, as long as it isn't disabled
+pub fn shortcut_action(menus: &[Menu], shortcut: Shortcut) -> Option<MenuAction> {
+    items(menus).into_iter().find(|i| i.shortcut == Some(shortcut) && !i.disabled).map(|i| i.action.clone())
+}
+
+pub fn menus(ui: &Ui, skins: &[(String, String)], languages: &[(String, String)]) -> Vec<Menu> {
+    use MenuItem::Separator;
+    let game = &ui.minesweeper_element;
+    let style = ui.renderer.style();
+    let volume = ui.renderer.sound_player().volume();
+
+    // Easy, Normal, Hard, with their sizes next to their shortcuts
+    let difficulties = [
+        ("difficulty.easy",   "9¬¬*¬¬9¬¬,  ¬9 ¬¬", Shortcut::Easy,   Difficulty::Easy),
+        ("difficulty.normal", "¬15*13, ¬¬40¬¬"   , Shortcut::Normal, Difficulty::Normal),
+        ("difficulty.hard",   "30*16, 100"       , Shortcut::Hard,   Difficulty::Hard),
+    ];
+    let mut difficulty_items: Vec<MenuItem> = difficulties.into_iter().map(|(key, size, shortcut, difficulty)| {
+        let is_current = game.difficulty() == difficulty && game.daily().is_none();
+        MenuItem::check(Label::key(key), is_current, MenuAction::Difficulty(difficulty)).with_other_text(size.to_owned()).with_shortcut(shortcut)
+    }).collect();
+    difficulty_items.push(MenuItem::check(Label::key("menu.custom"), game.difficulty().is_custom(), MenuAction::Custom));
+    difficulty_items.push(MenuItem::check(Label::key("menu.daily"),  game.daily().is_some(),        MenuAction::Daily));
+
+    let game_menu = Menu { key: "menu.game", items: vec![
+        MenuItem::button(Label::key("menu.new_game"), MenuAction::NewGame).with_shortcut(Shortcut::NewGame),
+        MenuItem::button(Label::key("menu.undo"),     MenuAction::Undo).with_shortcut(Shortcut::Undo).disabled(!game.can_undo()),
+        Separator,
+        MenuItem::Submenu(Label::key("menu.difficulty"), difficulty_items),
+        // Modes, which start a new game when picked
+        MenuItem::Submenu(Label::key("menu.mode"), GameMode::ALL.into_iter().map(|mode| {
+            MenuItem::check(Label::key(mode.label_key()), game.next_mode() == mode, MenuAction::Mode(mode))
+        }).collect()),
+        Separator,
+        // Assists, games played with these on don't count towards the best times
+        MenuItem::check(Label::key("menu.auto_flag"), game.assists().auto_flag, MenuAction::AutoFlag),
+        MenuItem::check(Label::key("menu.auto_open"), game.assists().auto_open, MenuAction::AutoOpen),
+        Separator,
+        MenuItem::button(Label::key("menu.statistics"), MenuAction::Statistics),
+        Separator,
+        MenuItem::check(Label::key("menu.screen_shake"), ui.renderer.shake_enabled, MenuAction::ScreenShake),
+        MenuItem::button(Label::key("menu.settings"), MenuAction::Settings),
+        MenuItem::button(Label::key("menu.controls"), MenuAction::Controls),
+        Separator,
+        MenuItem::button(Label::key("menu.exit"), MenuAction::Exit),
+    ]};
+
+    let faces = [
+        FaceType::Fox,
+        // FaceType::Bird,
+        FaceType::Nerd,
+    ];
+    let help_menu = Menu { key: "menu.help", items: vec![
+        MenuItem::button(Label::key("menu.hint"),  MenuAction::Hint).with_shortcut(Shortcut::Help),
+        MenuItem::button(Label::key("menu.about"), MenuAction::About),
+        Separator,
+        // Built-in skins have their names translated
+        MenuItem::Submenu(Label::key("menu.skin"), skins.iter().map(|(name, label)| {
+            MenuItem::check(Label::KeyOr(format!("skin.{}", name), label.clone()), style.skin() == name, MenuAction::Skin(name.clone()))
+        }).collect()),
+        MenuItem::check(Label::key("menu.number_cues"), style.number_cues(), MenuAction::NumberCues),
+        MenuItem::Submenu(Label::key("menu.face"), faces.into_iter().map(|face_type| {
+            MenuItem::check(Label::Key(format!("face.{}", face_type.name())), *style.face_type() == face_type, MenuAction::Face(face_type))
+        }).collect()),
+        // Languages are always in their own language, so they can be found whatever the current one is
+        MenuItem::Submenu(Label::key("menu.language"), languages.iter().map(|(code, name)| {
+            MenuItem::check(Label::Text(name.clone()), ui.renderer.lang().code() == code, MenuAction::Language(code.clone()))
+        }).collect()),
+    ]};
+
+    let mut scale_items = vec![
+        MenuItem::check(Label::key("menu.auto"), ui.state.auto_scale(), MenuAction::AutoScale),
+        Separator,
+    ];
+    scale_items.extend((1..=8).map(|i| {
+        let label = Label::Text(format!(" {}{}* ", if i == 1 {"¬"} else {""}, i));
+        MenuItem::check(label, ui.state.scale() == i as f32, MenuAction::Scale(i as f32))
+    }));
+    scale_items.extend([Separator, MenuItem::button(Label::key("menu.fit"), MenuAction::Fit)]);
+    let scale_menu = Menu { key: "menu.scale", items: scale_items };
+
+    // Clicking a volume steps it up, wrapping back round to silent
+    let mut sound_items = vec![
+        MenuItem::check(Label::key("menu.mute"), volume.muted, MenuAction::Mute),
+        Separator,
+        MenuItem::cycle(Label::key("sound.master"), Volume::percent(volume.master), MenuAction::Volume(None)),
+    ];
+    sound_items.extend(SoundCategory::ALL.into_iter().map(|category| {
+        MenuItem::cycle(Label::key(category.label_key()), Volume::percent(volume.category(category)), MenuAction::Volume(Some(category)))
+    }));
+    let sound_menu = Menu { key: "menu.sound", items: sound_items };
+
+    vec![game_menu, help_menu, scale_menu, sound_menu]
+}
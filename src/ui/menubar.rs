@@ -1,6 +1,18 @@
 use macroquad::math::{vec2, Rect, Vec2};
 
-use super::{elements::Align, hash_string, renderer::{style::SHADOW, DrawShape, Renderer}, state::{ButtonState, Id, SelectedItem, State}};
+use super::{elements::Align, hash_string, menu::{Item, Menu, MenuAction, MenuItem}, renderer::{sound::SoundEffect, style::SHADOW, DrawShape, Renderer}, state::{ButtonState, Id, SelectedItem, State}};
+
+// A menu item's dropdown, or a submenu in one, which its items go down
+#[derive(Default, Clone, Copy)]
+struct Dropdown {
+    id: Id,
+    x: f32,
+    width: f32,
+
+    start_y: f32,
+    current_y: f32,
+    next_y: f32,
+}
 
 #[derive(Default)]
 pub struct Menubar {
@@ -8,30 +20,75 @@ pub struct Menubar {
 
     item_current: Option<Id>,
     item_current_prev: Option<Id>,
+    // The key of the menu that's open, for things that want to know when it's been opened
+    open_menu: Option<&'static str>,
 
     item_current_x: f32,
     item_next_x: f32,
 
-    dropdown_width: f32,
-    
-    dropdown_start_y: f32,
-    dropdown_current_y: f32,
-    dropdown_next_y: f32,
-    
-    dropdown_rect: Rect,
+    // The dropdown that items are being added to, and the ones it's a submenu of
+    dropdown: Dropdown,
+    dropdown_parents: Vec<Dropdown>,
+    // The submenu that's open in each level of the dropdown
+    submenus_open: Vec<Id>,
+
+    // Every dropdown that's been drawn this frame, for telling when somewhere else gets clicked
+    dropdown_rects: Vec<Rect>,
 }
 
 impl Menubar {
     pub fn height(&self) -> f32 {
         self.height
     } 
+    pub fn open_menu(&self) -> Option<&'static str> {
+        self.open_menu
+    }
 
-    pub fn begin(&mut self) {
+    // Draws the menus, returning whatever was picked from them
+    pub fn show(&mut self, menus: &[Menu], state: &mut State, renderer: &mut Renderer) -> Option<MenuAction> {
+        self.begin();
+        self.open_menu = None;
+        let mut action = None;
+        for menu in menus {
+            let size = dropdown_size(&menu.items, renderer);
+            if self.item(menu.label(renderer.lang()), size.x, state, renderer) {
+                self.open_menu = Some(menu.key);
+                action = action.or(self.items(&menu.items, state, renderer));
+                self.finish_item(state, renderer);
+            }
+        }
+        self.finish(state, renderer);
+        action
+    }
+
+    fn items(&mut self, items: &[MenuItem], state: &mut State, renderer: &mut Renderer) -> Option<MenuAction> {
+        let mut action = None;
+        for item in items {
+            match item {
+                MenuItem::Item(item) => {
+                    if self.dropdown_item(item, state, renderer) {
+                        action = Some(item.action.clone());
+                    }
+                }
+                MenuItem::Separator => self.dropdown_separator(renderer),
+                MenuItem::Submenu(label, items) => {
+                    if self.submenu(label.text(renderer.lang()), dropdown_size(items, renderer), state, renderer) {
+                        action = action.or(self.items(items, state, renderer));
+                        self.finish_submenu(state, renderer);
+                    }
+                }
+            }
+        }
+        action
+    }
+
+    fn begin(&mut self) {
         self.item_current_prev = self.item_current;
         self.item_next_x = 0.0;
+        self.dropdown_rects.clear();
     }
 
-    pub fn finish(&mut self, state: &mut State, renderer: &mut Renderer) {
+    fn finish(&mut self, state: &mut State, renderer: &mut Renderer) {
         renderer.draw(super::renderer::DrawShape::Rect {
             x: self.item_next_x,
             y: 0.0,
@@ -40,78 +97,103 @@ impl Menubar {
             color: renderer.style().menubar(false).0,
         });
 
-        // If anywhere that's not the dropdown has been clicked, deselect the menubar
+        // If anywhere that's not the dropdown or one of its submenus has been clicked, deselect the menubar
         if self.item_current.is_some() && self.item_current_prev.is_some()
-        && state.mouse_pressed(macroquad::input::MouseButton::Left) && !state.mouse_in_rect(self.dropdown_rect) {
+        && state.mouse_pressed(macroquad::input::MouseButton::Left) && !self.dropdown_rects.iter().any(|r| state.mouse_in_rect(*r)) {
             state.active_item = SelectedItem::Unavailable;
             self.item_current = None;
         }
+        // Escape closes the last submenu, then the dropdown, before it closes any popups
+        if self.item_current.is_some() && state.escape_pressed() && self.submenus_open.pop().is_none() {
+            self.item_current = None;
+        }
+        if self.item_current.is_none() {
+            self.submenus_open.clear();
+        }
     }
 
-    pub fn item(&mut self, text: String, dropdown_width: f32, state: &mut State, renderer: &mut Renderer) -> bool {
+    fn item(&mut self, text: String, dropdown_width: f32, state: &mut State, renderer: &mut Renderer) -> bool {
         self.item_current_x = self.item_next_x;
         
         let size = renderer.text_renderer.text_size(&text, None) + vec2(4.0, 2.0);
         self.item_next_x += size.x;
         self.height = self.height.max(size.y);
         
-        self.dropdown_start_y = self.height + renderer.style().dropdown_background().padding;
-        self.dropdown_next_y = self.dropdown_start_y;
-        self.dropdown_width = dropdown_width;
-        
         let id = hash_string(&text);
+        let pad = renderer.style().dropdown_background().padding;
+        // Under the item, unless that'd go off the right of the window
+        let x = self.item_current_x.min(state.screen_size().x - dropdown_width - pad * 2.0).max(0.0);
+        let start_y = self.height + pad;
+        self.dropdown = Dropdown { id, x, width: dropdown_width, start_y, current_y: start_y, next_y: start_y };
+        self.dropdown_parents.clear();
+
         let rect = Rect::new(self.item_current_x, 0.0, size.x, size.y);
         let hovered = state.mouse_in_rect(rect);
         let button_state = state.button_state(id, hovered, false, false);
         // If a dropdown is open and the mouse has hovered this menu item, or if this menu item's been clicked, set THIS to be the current one.
         if (button_state == ButtonState::Hovered && self.item_current.is_some()) || button_state == ButtonState::Clicked {
+            if self.item_current != Some(id) {
+                self.submenus_open.clear();
+            }
             self.item_current = Some(id);
         }
         
         let (background, text_col, _) = renderer.style().menubar(self.item_current == Some(id) || state.hot_item == id);
 
-        renderer.draw(super::renderer::DrawShape::text(rect.x + 2.0, rect.y + 1.0, text, None, None, None,text_col));
+        renderer.draw(super::renderer::DrawShape::text(rect.x + 2.0, rect.y + 1.0, text, None, None, text_col));
         renderer.draw(super::renderer::DrawShape::rect(rect, background));
 
         self.item_current == Some(id)
     }
 
-    pub fn finish_item(&mut self, state: &mut State, renderer: &mut Renderer) {
-        // If the dropdown doesn't go down at all, it has not dropdown items and therefore doesn't have a rect,
-        // so we don't really care about doing anything
-        if self.dropdown_next_y == self.dropdown_start_y {
-            self.dropdown_rect = Rect::new(0.0, 0.0, 0.0, 0.0);
-            return;
-        }
+    fn finish_item(&mut self, state: &mut State, renderer: &mut Renderer) {
+        self.finish_dropdown(state, renderer);
+    }
 
+    fn finish_dropdown(&mut self, state: &mut State, renderer: &mut Renderer) {
         let pad = renderer.style().dropdown_background().padding;
-        self.dropdown_rect =  Rect::new(
-            self.item_current_x,
-            self.dropdown_start_y - pad,
-            self.dropdown_width  + pad * 2.0,
-            self.dropdown_next_y + pad * 2.0 - self.dropdown_start_y,
+        let dropdown = self.dropdown;
+        let rect = Rect::new(
+            dropdown.x,
+            dropdown.start_y - pad,
+            dropdown.width  + pad * 2.0,
+            dropdown.next_y + pad * 2.0 - dropdown.start_y,
         );
+        // If the dropdown doesn't go down at all, it has no dropdown items and therefore doesn't have a rect,
+        // so we don't really care about doing anything
+        if dropdown.next_y == dropdown.start_y {
+            return;
+        }
+        self.dropdown_rects.push(rect);
 
         // Draw the dropdown box and it's shadow
-        renderer.draw(DrawShape::nineslice(self.dropdown_rect, renderer.style().dropdown_background()));
-        renderer.draw(DrawShape::rect(self.dropdown_rect.offset(Vec2::splat(3.0)), SHADOW));
+        renderer.draw(DrawShape::nineslice(rect, renderer.style().dropdown_background()));
+        renderer.draw(DrawShape::rect(rect.offset(Vec2::splat(3.0)), SHADOW));
 
         // Make it so the box captures the hot item
-        state.hot_item.make_unavailable_if_none_and(state.mouse_in_rect(self.dropdown_rect));
+        state.hot_item.make_unavailable_if_none_and(state.mouse_in_rect(rect));
     }
 
-    fn dropdown_item(&mut self, text: String, other_text: Option<String>, icon: bool, state: &mut State, renderer: &mut Renderer) -> bool {
-        self.dropdown_current_y = self.dropdown_next_y;
+    // Where the next item in the dropdown goes
+    fn dropdown_rect(&mut self, text: &String, renderer: &Renderer) -> Rect {
+        self.dropdown.current_y = self.dropdown.next_y;
         let rect = Rect::new(
-            self.item_current_x + renderer.style().dropdown_background().padding,
-            self.dropdown_current_y,
-            self.dropdown_width,
-            renderer.text_renderer.text_size(&text, None).y + 3.0,
+            self.dropdown.x + renderer.style().dropdown_background().padding,
+            self.dropdown.current_y,
+            self.dropdown.width,
+            item_height(text, renderer),
         );
-        self.dropdown_next_y += rect.h;
+        self.dropdown.next_y += rect.h;
+        rect
+    }
+
+    // Items that don't close the dropdown can be clicked over and over, like for stepping through values
+    fn dropdown_item(&mut self, item: &Item, state: &mut State, renderer: &mut Renderer) -> bool {
+        let text = item.label.text(renderer.lang());
+        let rect = self.dropdown_rect(&text, renderer);
 
         // I do different button logic here because they behave slightly differently than normal buttons
-        let id = hash_string(&format!("{:?}{}", self.item_next_x, text));
+        let id = hash_string(&format!("{:?}{}", self.dropdown.id, text));
 
         let mouse_down = state.mouse_down(macroquad::input::MouseButton::Left);
 
@@ -119,16 +201,26 @@ impl Menubar {
             if mouse_down {
                 state.active_item.assign(id);
             }
+            // Hovering something else closes any submenus from this level
+            self.submenus_open.truncate(self.dropdown_parents.len());
         }
 
-        let released = state.hot_item == id && state.active_item == id && !mouse_down;
+        // Disabled items still get hovered so nothing behind them does, but can't be clicked
+        let hovered = state.hot_item == id && !item.disabled;
+        let released = hovered && state.active_item == id && !mouse_down;
         if released {
-            self.item_current = None;
+            if !item.stays_open {
+                self.item_current = None;
+            }
+            renderer.sound_player_mut().play(SoundEffect::Click);
         }
 
-        let (background, text_col, other_text_col) = renderer.style().menubar(state.hot_item == id);
+        let (background, text_col, other_text_col) = match item.disabled {
+            false => renderer.style().menubar(hovered),
+            true  => (renderer.style().menubar(false).0, renderer.style().text_disabled(), renderer.style().text_disabled()),
+        };
 
-        if icon {
+        if item.checked {
             renderer.draw(super::renderer::DrawShape::Rect {
                 x: rect.x + 2.0,
                 y: rect.y + 3.0,
@@ -137,8 +229,8 @@ impl Menubar {
                 color: text_col,
             })
         }
-        renderer.draw(super::renderer::DrawShape::text(rect.x + 7.0, rect.y + 2.0, text, None, None, None, text_col ));
-        if let Some(other_text) = other_text {
+        renderer.draw(super::renderer::DrawShape::text(rect.x + 7.0, rect.y + 2.0, text, None, None, text_col ));
+        if let Some(other_text) = item.right_text() {
             super::elements::text(other_text, None, other_text_col, Align::End(rect.right() - 3.0), Align::Beg(rect.y + 2.0), renderer);
         }
         renderer.draw(super::renderer::DrawShape::rect(rect, background));
@@ -146,27 +238,87 @@ impl Menubar {
         released
     }
 
-    pub fn dropdown(&mut self, text: String, other_text: Option<String>, state: &mut State, renderer: &mut Renderer) -> bool {
-        self.dropdown_item(text, other_text, false, state, renderer)
-    }
-    pub fn dropdown_radio(&mut self, text: String, other_text: Option<String>, qualifier: bool, state: &mut State, renderer: &mut Renderer) -> bool {
-        self.dropdown_item(text, other_text, qualifier, state, renderer)
-    }
-    pub fn dropdown_toggle(&mut self, text: String, other_text: Option<String>, value: &mut bool, state: &mut State, renderer: &mut Renderer) -> bool {
-        let pressed = self.dropdown_item(text, other_text, *value, state, renderer);
-        if pressed { *value = !*value; }
-        pressed
-    }
-    pub fn dropdown_separator(&mut self, renderer: &mut Renderer) {
-        self.dropdown_current_y = self.dropdown_next_y;
+    fn dropdown_separator(&mut self, renderer: &mut Renderer) {
+        self.dropdown.current_y = self.dropdown.next_y;
         let source = renderer.style().dropdown_separator();
         let dest = Rect::new(
-            self.item_current_x + 2.0,
-            self.dropdown_current_y,
-            self.dropdown_width - 2.0,
+            self.dropdown.x + 2.0,
+            self.dropdown.current_y,
+            self.dropdown.width - 2.0,
             source.h,
         );
-        self.dropdown_next_y += dest.h;
+        self.dropdown.next_y += dest.h;
         renderer.draw(super::renderer::DrawShape::image_rect(dest, source, None));
     }
-}
\ No newline at end of file
+
+    // An item with an arrow that opens another dropdown next to it when it's hovered (or tapped).
+    // If it's open, the items after this go in the submenu until finish_submenu is called
+    fn submenu(&mut self, text: String, size: Vec2, state: &mut State, renderer: &mut Renderer) -> bool {
+        let rect = self.dropdown_rect(&text, renderer);
+        let id = hash_string(&format!("{:?}{}", self.dropdown.id, text));
+        let level = self.dropdown_parents.len();
+
+        if state.hot_item.assign_if_none_and(id, state.mouse_in_rect(rect)) && self.submenus_open.get(level) != Some(&id) {
+            self.submenus_open.truncate(level);
+            self.submenus_open.push(id);
+        }
+        let open = self.submenus_open.get(level) == Some(&id);
+
+        let (background, text_col, _) = renderer.style().menubar(state.hot_item == id || open);
+        // The arrow, pointing to where the submenu opens
+        for i in 0..3 {
+            let i = i as f32;
+            renderer.draw(DrawShape::rect(Rect::new(rect.right() - 5.0 + i, rect.y + 2.0 + i, 1.0, 5.0 - i * 2.0), text_col));
+        }
+        renderer.draw(super::renderer::DrawShape::text(rect.x + 7.0, rect.y + 2.0, text, None, None, text_col));
+        renderer.draw(super::renderer::DrawShape::rect(rect, background));
+
+        if !open {
+            return false;
+        }
+
+        // To the right of the dropdown, or the left if there's no room, and kept inside the window either way
+        let pad = renderer.style().dropdown_background().padding;
+        let (screen, outer) = (state.screen_size(), size + pad * 2.0);
+        let (right, left) = (rect.right() + pad, self.dropdown.x - outer.x);
+        let x = match right + outer.x > screen.x && left >= 0.0 {
+            true  => left,
+            false => right.min(screen.x - outer.x).max(0.0),
+        };
+        let y = (rect.y - pad).min(screen.y - outer.y).max(self.height);
+
+        let submenu = Dropdown { id, x, width: size.x, start_y: y + pad, current_y: y + pad, next_y: y + pad };
+        self.dropdown_parents.push(std::mem::replace(&mut self.dropdown, submenu));
+        true
+    }
+
+    fn finish_submenu(&mut self, state: &mut State, renderer: &mut Renderer) {
+        self.finish_dropdown(state, renderer);
+        if let Some(parent) = self.dropdown_parents.pop() {
+            self.dropdown = parent;
+        }
+    }
+}
+
+fn item_height(text: &String, renderer: &Renderer) -> f32 {
+    renderer.text_renderer.text_size(text, None).y + 3.0
+}
+
+// How big a dropdown needs to be to fit its items in, not counting the padding around them
+fn dropdown_size(items: &[MenuItem], renderer: &Renderer) -> Vec2 {
+    let lang = renderer.lang();
+    let width = |text: &String| renderer.text_renderer.text_size(text, None).x;
+    items.iter().map(|item| match item {
+        MenuItem::Item(item) => {
+            let text = item.label.text(lang);
+            let other_text_width = item.right_text().map(|t| width(&t) + 4.0).unwrap_or(0.0);
+            vec2(width(&text) + 10.0 + other_text_width, item_height(&text, renderer))
+        }
+        // With room for the arrow
+        MenuItem::Submenu(label, _) => {
+            let text = label.text(lang);
+            vec2(width(&text) + 16.0, item_height(&text, renderer))
+        }
+        MenuItem::Separator => vec2(0.0, renderer.style().dropdown_separator().h),
+    }).fold(Vec2::ZERO, |size, item| vec2(size.x.max(item.x), size.y + item.y))
+}
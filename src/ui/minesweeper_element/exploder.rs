@@ -3,7 +3,7 @@
 use indexmap::IndexMap;
 use macroquad::time::get_frame_time;
 
-use crate::{minesweeper::Minesweeper, ui::renderer::Renderer};
+use crate::{minesweeper::{Minesweeper, Tile}, ui::renderer::{sound::SoundEffect, Renderer}};
 
 #[derive(Default)]
 pub struct Exploder {
@@ -25,6 +25,11 @@ impl Exploder {
         self.map.get(key).and_then(|(_, e)| Some(*e))
     }
 
+    // How many bombs there are to explode, and how many have exploded so far
+    pub fn progress(&self) -> (usize, usize) {
+        (self.map.len(), self.map_skip)
+    }
+
     pub fn map_mut(&mut self) -> &mut IndexMap<usize, (f32, bool)> {
         &mut self.map
     }
@@ -62,6 +67,18 @@ impl Exploder {
         self.radius_expansion = (usize::max(game.width(), game.height()) as f32).sqrt() * 2.0;
     }
 
+    // Blows up just the one bomb, for when a life is lost but the game carries on
+    pub fn explode(&mut self, index: usize) {
+        // It goes on the end, as every bomb before it will already have exploded
+        self.map.insert(index, (0.0, false));
+    }
+
+    // Takes back the explosions of bombs that aren't hit any more, after a move's been undone
+    pub fn undo(&mut self, game: &Minesweeper) {
+        self.map.retain(|i, _| game.board().get(*i) == Some(&Tile::Hit));
+        self.map_skip = self.map.values().filter(|(_, exploded)| *exploded).count();
+    }
+
     pub fn update(&mut self, renderer: &mut Renderer) {
         // If we've exploded all of the bombs, we don't need to do anything more!!
         if self.map_skip == self.map.len() {
@@ -90,7 +107,7 @@ impl Exploder {
         if self.map_skip != prev_skip && self.effect_timer > 0.1 {
             self.effect_timer = 0.0;
             renderer.shake(1.0);
-            renderer.sound_player().play_explosion();
+            renderer.sound_player_mut().play(SoundEffect::Explosion);
         }
     }
 }
\ No newline at end of file
@@ -1,17 +1,104 @@
 use std::collections::HashSet;
 
-use macroquad::{camera::{set_camera, Camera2D}, color::WHITE, input::MouseButton, math::{Rect, Vec2}, texture::{draw_texture_ex, render_target, DrawTextureParams, RenderTarget}};
+use macroquad::{camera::{set_camera, Camera2D}, color::WHITE, input::{is_key_down, is_key_pressed, mouse_wheel, KeyCode, MouseButton}, math::{vec2, Rect, Vec2}, shapes::draw_rectangle, texture::{draw_texture_ex, render_target, DrawTextureParams, RenderTarget}};
 
-use crate::{minesweeper::{get_index_from_offset, Difficulty, GameState, Minesweeper, SetFlagMode, Tile, NEIGHBOUR_OFFSETS}, ui::{elements::{aligned_rect, Align}, hash_string, renderer::{style::{MINEFIELD_TILE_SIZE, STATUS_V_PAD}, DrawShape, Renderer}, state::{Id, State}}};
+use crate::{minesweeper::{get_index_from_offset, Difficulty, GameState, Minesweeper, SetFlagMode, Tile, NEIGHBOUR_OFFSETS}, ui::{bindings::{Action, Bindings}, elements::{aligned_rect, is_ctrl_down, Align}, hash_string, renderer::{sound::SoundEffect, style::{MINEFIELD_TILE_SIZE, NUMBER_CUE_Y, SHADOW, STATUS_V_PAD}, DrawShape, Renderer}, state::{ButtonState, Id, State}}};
 
 use super::exploder::Exploder;
 
+const KEYS_UP:    &[KeyCode] = &[KeyCode::Up,    KeyCode::W];
+const KEYS_DOWN:  &[KeyCode] = &[KeyCode::Down,  KeyCode::S];
+const KEYS_LEFT:  &[KeyCode] = &[KeyCode::Left,  KeyCode::A];
+const KEYS_RIGHT: &[KeyCode] = &[KeyCode::Right, KeyCode::D];
+const KEYS_NEXT:  &[KeyCode] = &[KeyCode::N];
+const KEYS_ZOOM_IN:  &[KeyCode] = &[KeyCode::Equal, KeyCode::KpAdd];
+const KEYS_ZOOM_OUT: &[KeyCode] = &[KeyCode::Minus, KeyCode::KpSubtract];
+const KEYS_FIT:      &[KeyCode] = &[KeyCode::Key0,  KeyCode::Kp0];
+// Holding one of these lets the minefield be dragged around
+const KEYS_PAN:      &[KeyCode] = &[KeyCode::LeftShift, KeyCode::RightShift];
+
+// The zoom levels stepped between when zooming in and out, fitting the minefield can give anything in between
+const ZOOM_LEVELS: &[f32] = &[0.25, 0.5, 1.0, 2.0, 3.0, 4.0];
+// Huge minefields don't need to fit on the screen all at once, as they can be scrolled around.
+// This is the size of a hard one, so auto scaling doesn't shrink everything down to fit them
+const MIN_VIEW_SIZE: Vec2 = vec2(270.0, 144.0);
+const SCROLLBAR_WIDTH: f32 = 3.0;
+
+// What the keyboard wants the minefield to do this frame
+#[derive(Default)]
+struct KeyboardInput {
+    movement: (isize, isize),
+    dig:   bool,
+    flag:  bool,
+    chord: bool,
+    next:  bool,
+    zoom:  isize,
+    fit:   bool,
+}
+
+impl KeyboardInput {
+    fn read(bindings: &Bindings, state: &mut State) -> KeyboardInput {
+        let pressed = |keys: &[KeyCode]| keys.iter().any(|k| is_key_pressed(*k));
+        // Holding down a direction keeps on moving
+        let held = state.key_held();
+        let moved = |keys: &[KeyCode]| pressed(keys) || held.is_some_and(|k| keys.contains(&k));
+
+        KeyboardInput {
+            movement: (
+                moved(KEYS_RIGHT) as isize - moved(KEYS_LEFT) as isize,
+                moved(KEYS_DOWN)  as isize - moved(KEYS_UP)   as isize,
+            ),
+            dig:   bindings.key_pressed(Action::Dig),
+            flag:  bindings.key_pressed(Action::Flag),
+            chord: bindings.key_pressed(Action::Chord),
+            next:  pressed(KEYS_NEXT),
+            zoom:  pressed(KEYS_ZOOM_IN) as isize - pressed(KEYS_ZOOM_OUT) as isize,
+            fit:   pressed(KEYS_FIT),
+        }
+    }
+
+    fn any(&self) -> bool {
+        self.movement != (0, 0) || self.dig || self.flag || self.chord || self.next
+    }
+}
+
+// Everything other than the board itself that changes how the tiles look, if none of it's changed since last frame
+// (and nothing's being chorded) then none of the tiles need redrawing
+#[derive(Clone, Copy, PartialEq)]
+struct Snapshot {
+    revision:    usize,
+    state:       GameState,
+    exploder:    (usize, usize),
+    losing_tile: Option<usize>,
+    style:       u32,
+}
+
 pub struct Minefield {
     flag_mode:   Option<SetFlagMode>,
     losing_tile: Option<usize>,
     chording:     bool,
     chorded:      bool,
     about_to_dig: bool,
+    // The sound for whatever was done to the minefield this frame
+    sound:        Option<SoundEffect>,
+    // The keyboard's selected tile, and whether the keyboard or the mouse was used last
+    cursor:          usize,
+    keyboard:        bool,
+    last_mouse_pos:  Vec2,
+    // The viewport, which can be zoomed in and out and moved around for big minefields.
+    // The pan is the top left of the view, in minefield pixels
+    view:            Rect,
+    zoom:            f32,
+    pan:             Vec2,
+    fit_requested:   bool,
+    // The minefield pixel being dragged around with the mouse, or how far along a scrollbar's thumb was grabbed
+    pan_grab:        Option<Vec2>,
+    scroll_grab:     f32,
+
+    // What's been drawn to each tile of the render target, None if it needs drawing
+    drawn:         Vec<Option<(u32, Option<u32>)>>,
+    snapshot:      Option<Snapshot>,
+    chorded_tiles: HashSet<usize>,
 
     id: Id,
     render_target: RenderTarget,
@@ -25,7 +112,20 @@ impl Minefield {
             chording:        false,
             chorded:         false,
             about_to_dig:    false,
+            sound:           None,
+            cursor:          0,
+            keyboard:        false,
+            last_mouse_pos:  Vec2::ZERO,
+            view:            Rect::default(),
+            zoom:            1.0,
+            pan:             Vec2::ZERO,
+            fit_requested:   false,
+            pan_grab:        None,
+            scroll_grab:     0.0,
             id:              hash_string(&format!("if you're reading this... say hi :3 {:?}", macroquad::rand::rand())),
+            drawn:           vec![None; difficulty.values().width() * difficulty.values().height()],
+            snapshot:        None,
+            chorded_tiles:   HashSet::new(),
             render_target:   Minefield::render_target(difficulty),
         }
     }
@@ -37,7 +137,11 @@ impl Minefield {
         self.render_target.texture.size()
     }
     pub fn min_size(&self, renderer: &Renderer) -> Vec2 {
-        self.size() + renderer.style().minefield_border().padding * 2.0
+        self.size().min(MIN_VIEW_SIZE) + renderer.style().minefield_border().padding * 2.0
+    }
+    // Zooms so the whole minefield fits in the view next update
+    pub fn fit(&mut self) {
+        self.fit_requested = true;
     }
     pub fn tile_pos(&self, index: usize, game: &Minesweeper) -> Vec2 {
         MINEFIELD_TILE_SIZE as f32 * Vec2::new(
@@ -58,89 +162,170 @@ impl Minefield {
     pub fn new_game(&mut self, difficulty: Difficulty) {
         self.flag_mode     = None;
         self.losing_tile   = None;
+        self.cursor        = 0;
+        self.pan           = Vec2::ZERO;
+        self.drawn         = vec![None; difficulty.values().width() * difficulty.values().height()];
+        self.snapshot      = None;
         self.render_target = Minefield::render_target(difficulty);
     }
 
-    pub fn update(&mut self, area: Rect, game: &mut Minesweeper, exploder: &mut Exploder, state: &mut State, renderer: &mut Renderer) {
+    #[allow(clippy::too_many_arguments)]
+    pub fn update(&mut self, area: Rect, flag_mode: bool, bindings: &Bindings, game: &mut Minesweeper, exploder: &mut Exploder, state: &mut State, renderer: &mut Renderer) {
         // renderer.draw(DrawShape::rect(area, macroquad::color::Color::from_rgba(255, 0, 0, 128)));
 
-        let size = self.size();
+        let padding = renderer.style().minefield_border().padding;
+        let available = (vec2(area.w, area.h - STATUS_V_PAD) - padding * 2.0).max(Vec2::ONE);
+        if std::mem::take(&mut self.fit_requested) {
+            self.zoom = self.fit_zoom(available);
+        }
+
+        let size = (self.size() * self.zoom).min(available).floor();
         let rect = aligned_rect(Align::Mid(area.x + area.w / 2.0), Align::Mid(area.y + (area.h - STATUS_V_PAD) / 2.0), size.x, size.y);
         // Make sure it doesn't go above the area
-        let rect = Rect::new(rect.x, f32::max(rect.y, area.y + renderer.style().minefield_border().padding).floor(), rect.w, rect.h);
+        let rect = Rect::new(rect.x, f32::max(rect.y, area.y + padding).floor(), rect.w, rect.h);
+        self.view = rect;
 
         self.about_to_dig = false;
 
         let mut chorded_tiles: HashSet<usize> = HashSet::new();
+        let (prev_game_state, prev_lives_used, prev_revision) = (game.state(), game.lives_used(), game.revision());
+        // The tile that was interacted with, and the bomb that went off if there was one, which isn't always the same tile
+        let (mut dug, mut bomb) = (None, None);
+        self.sound = None;
 
-        if state.hot_item.assign_if_none_and(self.id, state.mouse_in_rect(rect)) {
-            let hovered_tile_coord = ((state.mouse_pos() - rect.point()) / MINEFIELD_TILE_SIZE as f32).floor();
-            let selected_tile = hovered_tile_coord.y as usize * game.width() + hovered_tile_coord.x as usize;
+        // Whichever of the mouse and keyboard was used last gets to select the tile
+        let any_mouse_down = state.mouse_down(MouseButton::Left) || state.mouse_down(MouseButton::Middle) || state.mouse_down(MouseButton::Right);
+        if state.mouse_pos() != self.last_mouse_pos || any_mouse_down {
+            self.keyboard = false;
+        }
+        self.last_mouse_pos = state.mouse_pos();
+
+        // The keyboard's ignored while typing into a text field or using a popup, and while ctrl's held as that's for the shortcuts
+        let keys = match state.keyboard_captured() || is_ctrl_down() {
+            false => KeyboardInput::read(bindings, state),
+            true  => KeyboardInput::default(),
+        };
+        if keys.any() {
+            self.keyboard = true;
+        }
+
+        // The scrollbars go on top, so they get the mouse first
+        let scrolling = self.scrollbars(state, renderer);
+        let hovered = state.hot_item.assign_if_none_and(self.id, state.mouse_in_rect(rect));
+        let panning = self.zoom_and_pan(hovered, &keys, state);
+        self.clamp_pan();
 
-            // Draw the selector thingy
-            let selector_pos = rect.point() + self.tile_pos(selected_tile, game) - 1.0;
-            renderer.draw(DrawShape::image(selector_pos.x, selector_pos.y, renderer.style().minefield_selector(), None));
+        if self.keyboard {
+            bomb = self.keyboard_interact(&keys, game);
+            dug = Some(self.cursor);
+            self.scroll_to(self.cursor, game);
+            self.draw_selector(self.cursor, game, renderer);
+        } else if hovered && !scrolling && !panning {
+            let hovered_tile_coord = (((state.mouse_pos() - rect.point()) / self.zoom + self.pan) / MINEFIELD_TILE_SIZE as f32).floor();
+            let selected_tile = (hovered_tile_coord.y as usize).min(game.height() - 1) * game.width() + (hovered_tile_coord.x as usize).min(game.width() - 1);
+            // So the keyboard carries on from where the mouse left off
+            self.cursor = selected_tile;
+            self.draw_selector(selected_tile, game, renderer);
 
             // Interacting
-            let any_mouse_down = state.mouse_down(MouseButton::Left) || state.mouse_down(MouseButton::Middle) || state.mouse_down(MouseButton::Right);
             state.active_item.assign_if_none_and(self.id, any_mouse_down);
 
-            let prev_game_state = game.state();
-            let bomb_index = self.interact(state.active_item == self.id, selected_tile, rect.point(), &mut chorded_tiles, game, state, renderer);
+            // Touch screens have their own controls, where tapping flags and long pressing digs when in flag mode
+            let touch_bindings = Bindings::touch(flag_mode);
+            let bindings = match state.using_touch() {
+                true  => &touch_bindings,
+                false => bindings,
+            };
+            bomb = self.interact(state.active_item == self.id, selected_tile, bindings, &mut chorded_tiles, game, state, renderer);
+            dug = Some(selected_tile);
+        }
 
+        if let Some(bomb_index) = bomb {
             // If we've lost on this frame, start exploding bombs!
             if prev_game_state.is_playing() && game.state().is_lose() {
-                let bomb_index = bomb_index.unwrap_or(selected_tile);
                 self.losing_tile = Some(bomb_index);
                 exploder.initialise(bomb_index, game);
             }
+            // If we've only lost a life, just explode the one we hit
+            else if game.lives_used() > prev_lives_used {
+                exploder.explode(bomb_index);
+            }
         }
         // Explode :3
-        if game.state() == GameState::Lose {
-            exploder.update(renderer);
+        exploder.update(renderer);
+
+        // Only play a sound if something actually happened, and not if a bomb went off as the explosion's loud enough
+        if let Some(sound) = self.sound.take() {
+            if game.revision() != prev_revision && game.lives_used() == prev_lives_used && !game.state().is_lose() {
+                // Digging an empty tile opens up everything around it
+                let sound = match (sound, dug.and_then(|i| game.board().get(i))) {
+                    (SoundEffect::Dig, Some(Tile::Dug)) => SoundEffect::Open,
+                    (sound, _) => sound,
+                };
+                renderer.sound_player_mut().play(sound);
+            }
         }
 
         // Rendering the board
-        // Drawing the minefield to a texture is much better than a million billion trillion renderer.draw(DrawShape)s
-        set_camera(&Camera2D {
-            zoom:   2.0 / self.render_target.texture.size(),
-            target: self.render_target.texture.size() / 2.0,
-            render_target: Some(self.render_target.clone()),
-            ..Default::default()
-        });
-
-        let draw_tile = |index: usize, id: u32| {
-            let pos = self.tile_pos(index, game);
-            draw_texture_ex(&renderer.style().texture(), pos.x, pos.y, WHITE, DrawTextureParams {
-                source: Some(renderer.style().minefield_tile(id)),
-                ..Default::default()
-            });
+        // Drawing the minefield to a texture is much better than a million billion trillion renderer.draw(DrawShape)s.
+        // Even better is only drawing the tiles that look different to last time!
+        let snapshot = Snapshot {
+            revision: game.revision(),
+            state:    game.state(),
+            exploder: exploder.progress(),
+            losing_tile: self.losing_tile,
+            style:    renderer.style().revision(),
         };
+        // Redraw everything if the skin's changed (or it's a new game), otherwise check every tile if the game's changed,
+        // and if not then the only ones that could've changed are the ones being chorded
+        let check_all = match self.snapshot {
+            Some(s) if s.style != snapshot.style => { self.drawn.fill(None); true }
+            Some(s) => s != snapshot,
+            None    => true,
+        };
+        self.snapshot = Some(snapshot);
+        let to_check: Vec<usize> = match check_all {
+            true  => (0..game.board().len()).collect(),
+            false => self.chorded_tiles.symmetric_difference(&chorded_tiles).copied().collect(),
+        };
+        self.chorded_tiles = chorded_tiles;
 
-        // Draw each of the tiles
-        for (i, t) in game.board().iter().enumerate() {
-            // Draw the background of the tile
-            let background = match t {
-                _ if self.losing_tile == Some(i)                         => 3, // The losing tile
-                Tile::Dug | Tile::Numbered(_)                            => 2, // A dug tile
-                Tile::Flag if exploder.index_exploded(&i) != Some(true)  => 0, // A flag that's not got an exploded bomb below it
-                _ if exploder.contains(&i) || chorded_tiles.contains(&i) => 2, // A bomb or a tile being chorded
-                _                                                        => 0, // Unopened
-            };
-            draw_tile(i, background);
-
-            // Draw the icon on top of the tile
-            let icon = match (t, exploder.index_exploded(&i)) {
-                (Tile::Flag, None) if game.state().is_lose() => Some(6), // Incorrect flag
-                (Tile::Flag, Some(true))                     => Some(5), // Exploded flag
-                (Tile::Flag, _)                              => Some(4), // Flag
-                (_, Some(false))                             => Some(7), // Unexploded bomb
-                (_, Some(true))                              => Some(8), // Exploded bomb
-                (Tile::Numbered(n), _)                       => Some(*n as u32 + 9), // Number
-                (Tile::Dug, _)                               => Some(9),
-                _ => None,
+        let to_draw: Vec<(usize, (u32, Option<u32>))> = to_check
+            .into_iter()
+            .map(|i| (i, self.tile_look(i, game, exploder)))
+            .filter(|(i, look)| self.drawn.get(*i).is_some_and(|d| *d != Some(*look)))
+            .collect();
+        // If there's nothing to draw, don't bother switching the camera
+        if !to_draw.is_empty() {
+            set_camera(&Camera2D {
+                zoom:   2.0 / self.render_target.texture.size(),
+                target: self.render_target.texture.size() / 2.0,
+                render_target: Some(self.render_target.clone()),
+                ..Default::default()
+            });
+            let draw_tile = |index: usize, id: u32| {
+                let pos = self.tile_pos(index, game);
+                draw_texture_ex(&renderer.style().texture(), pos.x, pos.y, WHITE, DrawTextureParams {
+                    source: Some(renderer.style().minefield_tile(id)),
+                    ..Default::default()
+                });
             };
-            icon.map(|id| draw_tile(i, id));
+            for &(i, (background, icon)) in &to_draw {
+                draw_tile(i, background);
+                if let Some(icon) = icon {
+                    draw_tile(i, icon);
+                }
+                // Numbers get an underline as long as they are, for anyone who can't tell the colours apart
+                if let (Some(id @ 10..=17), true) = (icon, renderer.style().number_cues()) {
+                    let number = id - 9;
+                    let pos = self.tile_pos(i, game);
+                    let x = ((MINEFIELD_TILE_SIZE - number) / 2) as f32;
+                    draw_rectangle(pos.x + x, pos.y + NUMBER_CUE_Y, number as f32, 1.0, renderer.style().number_color(number as u8));
+                }
+            }
+        }
+        for (i, look) in to_draw {
+            self.drawn[i] = Some(look);
         }
 
         // Draw the texture and the border with the renderer
@@ -151,16 +336,214 @@ impl Minefield {
             rect.w + border_padding * 2.0,
             rect.h + border_padding * 2.0,
         );
-        renderer.draw(DrawShape::texture(rect.x, rect.y, self.render_target.texture.clone()));
+        let source = Rect::new(self.pan.x, self.pan.y, rect.w / self.zoom, rect.h / self.zoom);
+        renderer.draw(DrawShape::texture(rect, source, self.render_target.texture.clone()));
         renderer.draw(DrawShape::nineslice(border_rect, renderer.style().minefield_border()));
     }
 
-    // Handles mouse interaction with the minefield
+    // The background and icon a tile should be drawn with
+    fn tile_look(&self, i: usize, game: &Minesweeper, exploder: &Exploder) -> (u32, Option<u32>) {
+        let t = &game.board()[i];
+        // The background of the tile
+        let background = match t {
+            _ if self.losing_tile == Some(i)                              => 3, // The losing tile
+            Tile::Hit                                                     => 3, // A bomb that cost a life
+            Tile::Dug | Tile::Numbered(_)                                 => 2, // A dug tile
+            Tile::Flag if exploder.index_exploded(&i) != Some(true)       => 0, // A flag that's not got an exploded bomb below it
+            _ if exploder.contains(&i) || self.chorded_tiles.contains(&i) => 2, // A bomb or a tile being chorded
+            _                                                             => 0, // Unopened
+        };
+        // The icon on top of the tile
+        let icon = match (t, exploder.index_exploded(&i)) {
+            (Tile::Hit, Some(false))                     => Some(7), // A bomb that cost a life, about to explode
+            (Tile::Hit, _)                               => Some(8), // A bomb that cost a life
+            (Tile::Flag, None) if game.state().is_lose() => Some(6), // Incorrect flag
+            (Tile::Flag, Some(true))                     => Some(5), // Exploded flag
+            (Tile::Flag, _)                              => Some(4), // Flag
+            (_, Some(false))                             => Some(7), // Unexploded bomb
+            (_, Some(true))                              => Some(8), // Exploded bomb
+            (Tile::Numbered(n), _)                       => Some(*n as u32 + 9), // Number
+            (Tile::Dug, _)                               => Some(9),
+            _ => None,
+        };
+        (background, icon)
+    }
+
+    // Where a tile is on the screen, with the zoom and pan
+    fn tile_screen_rect(&self, index: usize, game: &Minesweeper) -> Rect {
+        let pos = self.view.point() + (self.tile_pos(index, game) - self.pan) * self.zoom;
+        let size = MINEFIELD_TILE_SIZE as f32 * self.zoom;
+        Rect::new(pos.x, pos.y, size, size)
+    }
+
+    fn draw_selector(&self, index: usize, game: &Minesweeper, renderer: &mut Renderer) {
+        let tile = self.tile_screen_rect(index, game);
+        let dest = Rect::new(tile.x - self.zoom, tile.y - self.zoom, tile.w + self.zoom * 2.0, tile.h + self.zoom * 2.0);
+        renderer.draw(DrawShape::image_rect(dest, renderer.style().minefield_selector(), None));
+    }
+
+    // The biggest zoom that shows the whole minefield, sticking to whole numbers when zooming in so it stays crisp
+    fn fit_zoom(&self, available: Vec2) -> f32 {
+        let zoom = (available / self.size()).min_element();
+        match zoom >= 1.0 {
+            true  => zoom.floor(),
+            false => zoom,
+        }
+    }
+
+    fn set_zoom(&mut self, zoom: f32, anchor: Vec2) {
+        // Keep the point under the anchor in the same place
+        let point = (anchor - self.view.point()) / self.zoom + self.pan;
+        self.zoom = zoom.clamp(ZOOM_LEVELS[0], ZOOM_LEVELS[ZOOM_LEVELS.len() - 1]);
+        self.pan = point - (anchor - self.view.point()) / self.zoom;
+    }
+
+    fn step_zoom(&mut self, steps: isize, anchor: Vec2) {
+        let zoom = match steps > 0 {
+            true  => ZOOM_LEVELS.iter().find (|z| **z > self.zoom + 0.01),
+            false => ZOOM_LEVELS.iter().rfind(|z| **z < self.zoom - 0.01),
+        };
+        if let Some(zoom) = zoom {
+            self.set_zoom(*zoom, anchor);
+        }
+    }
+
+    // How far the view can be moved before it goes off the minefield
+    fn max_pan(&self) -> Vec2 {
+        (self.size() - self.view.size() / self.zoom).max(Vec2::ZERO)
+    }
+
+    fn clamp_pan(&mut self) {
+        self.pan = self.pan.clamp(Vec2::ZERO, self.max_pan());
+    }
+
+    // Moves the view just enough to show a tile
+    fn scroll_to(&mut self, index: usize, game: &Minesweeper) {
+        let tile = self.tile_pos(index, game);
+        let view_size = self.view.size() / self.zoom;
+        self.pan = self.pan.max(tile + MINEFIELD_TILE_SIZE as f32 - view_size).min(tile);
+        self.clamp_pan();
+    }
+
+    // Zooming with the mouse wheel or keyboard, and dragging the minefield around while holding a pan key.
+    // Returns whether the minefield's being dragged, so it doesn't dig anything
+    fn zoom_and_pan(&mut self, hovered: bool, keys: &KeyboardInput, state: &mut State) -> bool {
+        if keys.fit {
+            self.fit_requested = true;
+        }
+        if keys.zoom != 0 {
+            self.step_zoom(keys.zoom, self.view.center());
+        }
+        let wheel = mouse_wheel().1;
+        if hovered && wheel != 0.0 {
+            self.step_zoom(wheel.signum() as isize, state.mouse_pos());
+        }
+
+        let pan_key = KEYS_PAN.iter().any(|k| is_key_down(*k));
+        if hovered && pan_key && state.mouse_pressed(MouseButton::Left) {
+            self.pan_grab = Some((state.mouse_pos() - self.view.point()) / self.zoom + self.pan);
+            state.active_item.assign(self.id);
+        }
+        let grab = match self.pan_grab {
+            Some(grab) => grab,
+            None => return false,
+        };
+        self.pan = grab - (state.mouse_pos() - self.view.point()) / self.zoom;
+        // Letting go still counts as panning, otherwise it'd dig
+        if !state.mouse_down(MouseButton::Left) {
+            self.pan_grab = None;
+        }
+        true
+    }
+
+    // Scrollbars along the bottom and right of the view when the minefield doesn't fit.
+    // Returns whether one's being dragged
+    fn scrollbars(&mut self, state: &mut State, renderer: &mut Renderer) -> bool {
+        let mut scrolling = false;
+        let max_pan = self.max_pan();
+
+        for axis in 0..2 {
+            if max_pan[axis] <= 0.0 {
+                continue;
+            }
+            let view = self.view;
+            let track = match axis {
+                0 => Rect::new(view.x, view.bottom() - SCROLLBAR_WIDTH, view.w, SCROLLBAR_WIDTH),
+                _ => Rect::new(view.right() - SCROLLBAR_WIDTH, view.y, SCROLLBAR_WIDTH, view.h),
+            };
+            let (track_start, track_len) = match axis {
+                0 => (track.x, track.w),
+                _ => (track.y, track.h),
+            };
+            let thumb_len = (view.size()[axis] / (self.size()[axis] * self.zoom) * track_len).max(SCROLLBAR_WIDTH * 2.0);
+            let thumb_start = self.pan[axis] / max_pan[axis] * (track_len - thumb_len);
+
+            let button_state = state.button_state(self.id.wrapping_add(1 + axis as u64), state.mouse_in_rect(track), false, true);
+            let mouse = state.mouse_pos()[axis] - track_start;
+            if button_state.clicked() {
+                // Grabbing the thumb drags it from there, clicking elsewhere on the track jumps the middle of it to the mouse
+                self.scroll_grab = match (thumb_start..thumb_start + thumb_len).contains(&mouse) {
+                    true  => mouse - thumb_start,
+                    false => thumb_len / 2.0,
+                };
+            }
+            let thumb_start = match button_state {
+                ButtonState::Clicked | ButtonState::Held | ButtonState::Released => {
+                    scrolling = true;
+                    let thumb_start = (mouse - self.scroll_grab).clamp(0.0, track_len - thumb_len);
+                    self.pan[axis] = thumb_start / (track_len - thumb_len) * max_pan[axis];
+                    thumb_start
+                }
+                _ => thumb_start,
+            };
+
+            let thumb = match axis {
+                0 => Rect::new(track.x + thumb_start, track.y, thumb_len, track.h),
+                _ => Rect::new(track.x, track.y + thumb_start, track.w, thumb_len),
+            };
+            renderer.draw(DrawShape::rect(thumb, renderer.style().text_disabled()));
+            renderer.draw(DrawShape::rect(track, SHADOW));
+        }
+        scrolling
+    }
+
+    // Handles keyboard interaction with the minefield, returning the bomb that went off if there was one
+    fn keyboard_interact(&mut self, keys: &KeyboardInput, game: &mut Minesweeper) -> Option<usize> {
+        // Moving, stopping at the edges
+        let (x, y) = (self.cursor % game.width(), self.cursor / game.width());
+        let x = x.saturating_add_signed(keys.movement.0).min(game.width()  - 1);
+        let y = y.saturating_add_signed(keys.movement.1).min(game.height() - 1);
+        self.cursor = y * game.width() + x;
+
+        // Jumping to the next unopened tile, wrapping around to the start
+        if keys.next {
+            let len = game.board().len();
+            if let Some(next) = (1..len).map(|i| (self.cursor + i) % len).find(|i| game.board()[*i] == Tile::Unopened) {
+                self.cursor = next;
+            }
+        }
+
+        if keys.flag && game.set_flag(SetFlagMode::Toggle, self.cursor) {
+            self.sound = Some(flag_sound(game, self.cursor));
+        }
+        if keys.chord {
+            self.sound = Some(SoundEffect::Chord);
+            return game.chord(self.cursor);
+        }
+        if keys.dig {
+            self.sound = Some(SoundEffect::Dig);
+            return game.dig(self.cursor);
+        }
+        None
+    }
+
+    // Handles mouse interaction with the minefield, returning the bomb that went off if there was one
+    #[allow(clippy::too_many_arguments)]
     pub fn interact(
         &mut self,
         is_active: bool,
         selected_tile: usize,
-        pos: Vec2,
+        bindings: &Bindings,
         chorded_tiles: &mut HashSet<usize>,
         game:     &mut Minesweeper,
         state:    &mut State,
@@ -174,13 +557,12 @@ impl Minefield {
 
         if self.chording && any_mouse_released {
             (self.chording, self.chorded) = (false, true);
+            self.sound = Some(SoundEffect::Chord);
             return game.chord(selected_tile);
         };
 
         // We only want to be chording if the minefield is active and we're holding the right button(s)
-        let chord_button_combo = state.mouse_down(MouseButton::Middle)
-                             || (state.mouse_down(MouseButton::Left) && state.mouse_down(MouseButton::Right));
-        self.chording = is_active && game.state().is_playing() && chord_button_combo;
+        self.chording = is_active && game.state().is_playing() && bindings.mouse_down(Action::Chord, state);
         
         // Draw the chorded tiles
         if self.chording {
@@ -205,30 +587,44 @@ impl Minefield {
         }
 
         // Digging
-        if is_active && state.mouse_released(MouseButton::Left) {
-            game.dig(selected_tile);
-            return Some(selected_tile);
+        if is_active && bindings.mouse_released(Action::Dig, state) {
+            // There's no middle click on touch screens, so tapping a number chords it instead
+            if state.using_touch() && matches!(game.board().get(selected_tile), Some(Tile::Numbered(_))) {
+                self.sound = Some(SoundEffect::Chord);
+                return game.chord(selected_tile);
+            }
+            self.sound = Some(SoundEffect::Dig);
+            return game.dig(selected_tile);
         }
         // If about to dig, draw a tile being dug
-        if is_active && state.mouse_down(MouseButton::Left) && game.diggable(selected_tile) {
+        if is_active && bindings.mouse_down(Action::Dig, state) && game.diggable(selected_tile) {
             self.about_to_dig = true;
-            let pos = pos + self.tile_pos(selected_tile, game);
-            renderer.draw(DrawShape::image(pos.x, pos.y, renderer.style().minefield_tile(1), None));
+            let dest = self.tile_screen_rect(selected_tile, game);
+            renderer.draw(DrawShape::image_rect(dest, renderer.style().minefield_tile(1), None));
         }
 
         // Flagging
-        if matches!(self.flag_mode, Some(SetFlagMode::Flag)) || !state.mouse_down(MouseButton::Right) {
+        if matches!(self.flag_mode, Some(SetFlagMode::Flag)) || !bindings.mouse_down(Action::Flag, state) {
             self.flag_mode = None;
         }
-        if is_active && state.mouse_pressed(MouseButton::Right) {
+        if is_active && bindings.mouse_pressed(Action::Flag, state) {
             self.flag_mode = match game.board().get(selected_tile).is_some_and(|t| *t != Tile::Flag) {
                 true  => Some(SetFlagMode::Flag),
                 false => Some(SetFlagMode::Remove),
             }
         }
         if let Some(flag_mode) = self.flag_mode {
-            game.set_flag(flag_mode, selected_tile);
+            if game.set_flag(flag_mode, selected_tile) {
+                self.sound = Some(flag_sound(game, selected_tile));
+            }
         }
         None
     }
+}
+
+fn flag_sound(game: &Minesweeper, index: usize) -> SoundEffect {
+    match game.board().get(index) {
+        Some(Tile::Flag) => SoundEffect::Flag,
+        _                => SoundEffect::Unflag,
+    }
 }
\ No newline at end of file
@@ -1,19 +1,88 @@
 use macroquad::math::{vec2, Rect, Vec2};
 
-use crate::minesweeper::{Difficulty, GameState, Minesweeper};
+use crate::{daily::{self, DAILY_DIFFICULTY}, minesweeper::{Assists, Difficulty, GameState, Minesweeper, INFINITE_LIVES}};
 
-use self::{exploder::Exploder, minefield::Minefield, status_bar::StatusBar};
+use self::{exploder::Exploder, minefield::Minefield, status_bar::{Clock, StatusBar}};
 
-use super::{renderer::Renderer, state::State};
+use super::{bindings::Bindings, renderer::Renderer, state::State};
 
 pub mod minefield;
 pub mod exploder;
 pub mod status_bar;
 
+pub const LIVES: usize = 3;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GameMode {
+    Classic,
+    // Lose if the board isn't cleared in time
+    Countdown,
+    // Clear as many boards as possible before the time runs out
+    TimeAttack,
+    // Digging a bomb costs one of a few lives instead of ending the game
+    Lives,
+    // Relaxed, with no timer, no losing, and all of the assists
+    Zen,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 5] = [GameMode::Classic, GameMode::Countdown, GameMode::TimeAttack, GameMode::Lives, GameMode::Zen];
+
+    pub fn name(&self) -> &'static str {match self {
+        GameMode::Classic    => "classic",
+        GameMode::Countdown  => "countdown",
+        GameMode::TimeAttack => "time attack",
+        GameMode::Lives      => "lives",
+        GameMode::Zen        => "zen",
+    }}
+    pub fn from_name(name: &str) -> Option<GameMode> {match name {
+        "classic"     => Some(GameMode::Classic),
+        "countdown"   => Some(GameMode::Countdown),
+        "time attack" => Some(GameMode::TimeAttack),
+        "lives"       => Some(GameMode::Lives),
+        "zen"         => Some(GameMode::Zen),
+        _ => None,
+    }}
+    pub fn label_key(&self) -> &'static str {match self {
+        GameMode::Classic    => "mode.classic",
+        GameMode::Countdown  => "mode.countdown",
+        GameMode::TimeAttack => "mode.time_attack",
+        GameMode::Lives      => "mode.lives",
+        GameMode::Zen        => "mode.zen",
+    }}
+    pub fn lives(&self) -> usize {match self {
+        GameMode::Lives => LIVES,
+        GameMode::Zen   => INFINITE_LIVES,
+        _ => 1,
+    }}
+
+    pub fn time_limit(&self, difficulty: Difficulty) -> Option<f32> {
+        if !matches!(self, GameMode::Countdown | GameMode::TimeAttack) {
+            return None;
+        }
+        Some(match difficulty {
+            Difficulty::Easy   => 60.0,
+            Difficulty::Normal => 240.0,
+            Difficulty::Hard   => 600.0,
+            Difficulty::Custom(v) => (v.bomb_count() as f32 * 6.0).clamp(30.0, 99.0 * 60.0),
+        })
+    }
+}
+
 pub struct MinesweeperElement {
     game:       Minesweeper,
     difficulty: Difficulty,
     timer:      Option<f32>,
+    // The date of the daily challenge being played, if it is one
+    daily:      Option<String>,
+    // The mode of the current game, and the one the next game will use
+    mode:       GameMode,
+    next_mode:  GameMode,
+    boards_cleared: usize,
+    // The 3BV of the boards cleared so far in time attack
+    bbbv_cleared: usize,
+    assists:    Assists,
+    bindings:   Bindings,
     
     minefield:  Minefield,
     exploder:   Exploder,
@@ -33,6 +102,13 @@ impl MinesweeperElement {
             game: Minesweeper::new(difficulty),
             difficulty,
             timer: None,
+            daily: None,
+            mode:      GameMode::Classic,
+            next_mode: GameMode::Classic,
+            boards_cleared: 0,
+            bbbv_cleared: 0,
+            assists:   Assists::default(),
+            bindings:  Bindings::default(),
 
             minefield,
             exploder:   Exploder::default(),
@@ -46,9 +122,63 @@ impl MinesweeperElement {
 
     pub fn difficulty(&self)    -> Difficulty         { self.difficulty }
     pub fn custom_values(&self) -> Option<Difficulty> { self.custom_values }
+    pub fn timer(&self)         -> Option<f32>        { self.timer }
+    pub fn game(&self)          -> &Minesweeper       { &self.game }
+    pub fn daily(&self)         -> Option<&str>       { self.daily.as_deref() }
+    pub fn mode(&self)          -> GameMode           { self.mode }
+    pub fn boards_cleared(&self) -> usize             { self.boards_cleared }
+    // In time attack it's the boards that were cleared that count, not the one that was being played when time ran out
+    pub fn bbbv(&self) -> usize {
+        match self.mode {
+            GameMode::TimeAttack => self.bbbv_cleared,
+            _ => self.game.bbbv(),
+        }
+    }
+    pub fn assists(&self)       -> Assists            { self.assists }
+    pub fn bindings(&self)      -> &Bindings          { &self.bindings }
+
+    pub fn set_bindings(&mut self, bindings: Bindings) { self.bindings = bindings }
+
+    pub fn next_mode(&self) -> GameMode { self.next_mode }
+    pub fn set_next_mode(&mut self, mode: GameMode) { self.next_mode = mode }
+
+    pub fn time_limit(&self) -> Option<f32> {
+        self.mode.time_limit(self.difficulty)
+    }
+
+    pub fn fit_minefield(&mut self) {
+        self.minefield.fit();
+    }
+
+    pub fn set_assists(&mut self, assists: Assists) {
+        self.assists = assists;
+        self.game.set_assists(self.active_assists());
+    }
+
+    // Zen always has every assist on, and the daily challenge never has any
+    fn active_assists(&self) -> Assists {
+        match (self.mode, &self.daily) {
+            (GameMode::Zen, _) => Assists { auto_flag: true, auto_open: true },
+            (_, Some(_))       => Assists::default(),
+            _                  => self.assists,
+        }
+    }
+
+    // The daily challenge is played without any help, so it can't be undone
+    pub fn can_undo(&self) -> bool {
+        self.daily.is_none() && self.game.can_undo()
+    }
+
+    pub fn undo(&mut self) -> bool {
+        if !self.can_undo() || !self.game.undo() {
+            return false;
+        }
+        self.exploder.undo(&self.game);
+        true
+    }
 
     pub fn game_in_progress(&self) -> bool {
-        self.game.state().is_playing() && self.game.turns() != 0
+        self.game.state().is_playing() && (self.game.turns() != 0 || self.boards_cleared != 0)
     }
 
     pub fn new_game_requested(&mut self) -> Option<Difficulty> {
@@ -62,7 +192,7 @@ impl MinesweeperElement {
     // The minimum size the area can be before clipping
     pub fn minimum_size(&self, renderer: &Renderer) -> Vec2 {
         let minefield_size  = self.minefield .min_size(renderer);
-        let status_bar_size = self.status_bar.min_size();
+        let status_bar_size = self.status_bar.min_size(&self.game);
         vec2(
             f32::max(minefield_size.x, status_bar_size.x),
             minefield_size.y + status_bar_size.y,
@@ -70,42 +200,86 @@ impl MinesweeperElement {
     }
 
     pub fn new_game(&mut self, difficulty: Difficulty) {
-        self.game = Minesweeper::new(difficulty);
         self.difficulty = difficulty;
-        self.minefield.new_game(difficulty);
-        self.exploder.reset();
+        self.daily = None;
+        self.mode = self.next_mode;
+        self.boards_cleared = 0;
+        self.bbbv_cleared = 0;
+        self.new_board();
 
         if difficulty.is_custom() {
             self.custom_values = Some(difficulty);
         }
     }
 
+    // The daily challenge is always played normally, whatever the mode is
+    pub fn new_daily(&mut self, date: &str) {
+        self.new_game(DAILY_DIFFICULTY);
+        self.game  = Minesweeper::new_seeded(DAILY_DIFFICULTY, daily::seed(date));
+        self.daily = Some(date.to_owned());
+        self.mode  = GameMode::Classic;
+        self.game.set_assists(self.active_assists());
+    }
+
+    fn new_board(&mut self) {
+        self.game = Minesweeper::new(self.difficulty).with_lives(self.mode.lives());
+        self.game.set_assists(self.active_assists());
+        self.minefield.new_game(self.difficulty);
+        self.exploder.reset();
+    }
+
     pub fn update(&mut self, area: Rect, state: &mut State, renderer: &mut Renderer) {
+        let prev_state = self.game.state();
+
         // Update the timer
         self.timer = match (self.game.turns(), self.game.state()) {
-            // If we haven't made a move yet, the time should be None
-            (0, _) => None,
+            // If we haven't made a move yet, the time should be None (unless we're onto another board in time attack)
+            (0, _) if self.boards_cleared == 0 => None,
             // If the game is being played, increment the timer
             (_, GameState::Playing) => Some(self.timer.unwrap_or(0.0) + macroquad::time::get_frame_time()),
             // Otherwise (meaning we've won or lost) keep the timer frozen, on a valid value
             _ => Some(self.timer.unwrap_or(0.0)),
         };
 
-        let status_area    = Rect::new(area.x, area.y,                 area.w, self.status_bar.min_size().y);
+        // Running out of time, blow up the bombs to show where they were
+        if let (Some(limit), Some(timer)) = (self.time_limit(), self.timer) {
+            if timer >= limit && self.game.state().is_playing() {
+                self.timer = Some(limit);
+                self.game.time_out();
+                // The first bomb, so it's the same one every time
+                if let Some(&bomb) = self.game.bombs().iter().min() {
+                    self.exploder.initialise(bomb, &mut self.game);
+                }
+            }
+        }
+
+        let status_area    = Rect::new(area.x, area.y,                 area.w, self.status_bar.min_size(&self.game).y);
         let minefield_area = Rect::new(area.x, area.y + status_area.h, area.w, area.h - status_area.h);
 
+        let clock = Clock {
+            // Zen's about taking your time, so it doesn't show
+            time:  self.timer.filter(|_| self.mode != GameMode::Zen),
+            limit: self.time_limit(),
+            boards_cleared: (self.mode == GameMode::TimeAttack).then_some(self.boards_cleared),
+        };
         // If the button was clicked, we want to start a new game
-        if self.status_bar.update(status_area, self.minefield.about_to_dig(), &self.game, self.timer, state, renderer) {
+        if self.status_bar.update(status_area, self.minefield.about_to_dig(), &self.game, clock, state, renderer) {
             self.new_game_request = Some(self.difficulty)
         }
 
-        let prev_state = self.game.state();
-        self.minefield.update(minefield_area, &mut self.game, &mut self.exploder, state, renderer);
-        let new_state  = self.game.state();
+        self.minefield.update(minefield_area, self.status_bar.flag_mode(), &self.bindings, &mut self.game, &mut self.exploder, state, renderer);
+
+        // In time attack, clearing a board deals the next one straight away
+        if self.mode == GameMode::TimeAttack && self.game.state().is_win() {
+            self.boards_cleared += 1;
+            self.bbbv_cleared += self.game.bbbv();
+            self.new_board();
+        }
+        let new_state = self.game.state();
 
         self.game_state_change = match prev_state != new_state {
             true  => Some(new_state),
             false => None,
         }
     }
-}
\ No newline at end of file
+}
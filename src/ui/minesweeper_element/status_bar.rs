@@ -1,16 +1,32 @@
 use macroquad::{math::{vec2, Rect, Vec2}, rand::gen_range, time::get_frame_time};
 
-use crate::{minesweeper::{GameState, Minesweeper}, ui::{elements::{aligned_rect, Align}, renderer::{style::{bomb_counter_digit, bomb_counter_size, timer_colon, timer_digit, CounterDigit, Face, BOMB_COUNTER_DIGIT_GAP, BOMB_COUNTER_DIGIT_OFFSET, BOMB_COUNTER_HEIGHT, FACE_BUTTON_SIZE, FACE_OFFSET, STATUS_V_PAD, TIMER_COLON_POSITION, TIMER_DIGIT_POSITIONS, TIMER_DIGIT_Y, TIMER_SIZE}, DrawShape, Renderer}, state::{ButtonState, State}}};
+use crate::{minesweeper::{GameState, Minesweeper, INFINITE_LIVES}, ui::{elements::{self, aligned_rect, Align}, hash_string, renderer::{sound::SoundEffect, style::{bomb_counter_size, CounterDigit, Face, BOMB_COUNTER_DIGIT_GAP, BOMB_COUNTER_DIGIT_OFFSET, BOMB_COUNTER_HEIGHT, FACE_BUTTON_SIZE, FACE_OFFSET, MINEFIELD_TILE_SIZE, STATUS_V_PAD, TIMER_COLON_POSITION, TIMER_DIGIT_POSITIONS, TIMER_DIGIT_Y, TIMER_SIZE, TIMER_WARNING}, DrawShape, Renderer}, state::{ButtonState, State}}};
 
 const BLINK_DURATION:        f32   = 0.1;
 const SPAM_MAX_TIME:         f32   = 0.5;
 const SPAM_ANGER_CLICKS:     usize = 10;
 const SPAM_ANGER_RESET_TIME: f32   = 1.0;
+// When counting down, the timer starts flashing when there's this little time left
+const TIMER_WARNING_TIME:    f32   = 10.0;
+// The lives left go in a row of their own under the bomb counter
+const LIVES_HEIGHT:          f32   = 7.0;
+
+// What the timer should show
+#[derive(Clone, Copy, Default)]
+pub struct Clock {
+    pub time: Option<f32>,
+    // In the timed modes the timer counts down to zero from this
+    pub limit: Option<f32>,
+    // The score in time attack
+    pub boards_cleared: Option<usize>,
+}
 
 #[derive(Default)]
 pub struct StatusBar {
     blink_timer: f32,
     blink_next: f32,
+    // On touch screens, whether tapping flags instead of digs
+    flag_mode: bool,
 
     spam_timer:   f32,
     spam_counter: usize,
@@ -18,7 +34,8 @@ pub struct StatusBar {
 }
 
 impl StatusBar {
-    pub fn height(&self) -> f32 {
+    // The height of the row with the button, bomb counter and timer in it
+    fn row_height(&self) -> f32 {
         let (button_height, bomb_counter_height, timer_height) = (
             FACE_BUTTON_SIZE.y,
             BOMB_COUNTER_HEIGHT,
@@ -30,26 +47,37 @@ impl StatusBar {
         max + 2.0 * STATUS_V_PAD
     }
 
-    pub fn min_size(&self) -> Vec2 {
+    pub fn height(&self, game: &Minesweeper) -> f32 {
+        match shows_lives(game) {
+            true  => self.row_height() + LIVES_HEIGHT,
+            false => self.row_height(),
+        }
+    }
+
+    pub fn min_size(&self, game: &Minesweeper) -> Vec2 {
         // TODO: Maybe make this change depending on the number of digits in the bomb counter
-        vec2(75.0, self.height())
+        vec2(75.0, self.height(game))
     }
 
+    pub fn flag_mode(&self) -> bool { self.flag_mode }
+
     pub fn reset_blink_timer(&mut self) {
         self.blink_timer = 0.0;
         self.blink_next = gen_range(1.0, 10.0);
     }
 
     // Returns whether a new game was requested
-    pub fn update(&mut self, area: Rect, minefield_active: bool, game: &Minesweeper, timer: Option<f32>, state: &mut State, renderer: &mut Renderer) -> bool {
+    pub fn update(&mut self, area: Rect, minefield_active: bool, game: &Minesweeper, clock: Clock, state: &mut State, renderer: &mut Renderer) -> bool {
         // renderer.draw(DrawShape::rect(area, macroquad::color::Color::from_rgba(0, 0, 255, 128)));
 
-        let height = self.height();
-        let y = Align::Mid(area.y + height / 2.0);
+        let y = Align::Mid(area.y + self.row_height() / 2.0);
 
         let new_game = self.button(   Align::Mid(area.x + area.w / 2.0),         y, minefield_active, game, state, renderer);
+        if state.using_touch() {
+            self.flag_mode_button(    Align::End(area.x + area.w / 2.0 - FACE_BUTTON_SIZE.x / 2.0 - 2.0), y, state, renderer);
+        }
         StatusBar::bomb_counter(game, Align::Mid(area.x + area.w * (1.0 / 6.0)), y, renderer);
-        StatusBar::timer(timer,       Align::Mid(area.x + area.w * (5.0 / 6.0)), y, renderer);
+        StatusBar::timer(clock,       Align::Mid(area.x + area.w * (5.0 / 6.0)), y, renderer);
         new_game
     }
 
@@ -87,10 +115,10 @@ impl StatusBar {
         let rect = rect.offset(offset);
         
         let face = match (game.state(), eek) {
-            (GameState::Lose, _) => Face::Lose,
-            (GameState::Win,  _) => Face::Win,
-            (_, true)            => Face::Scared,
-            (_, false)           => Face::Idle,
+            (GameState::Lose(_), _) => Face::Lose,
+            (GameState::Win,     _) => Face::Win,
+            (_, true)               => Face::Scared,
+            (_, false)              => Face::Idle,
         };
         let button_held = matches!(button_state, ButtonState::Clicked | ButtonState::Held | ButtonState::Released);
         let (face, eyes) = renderer.style().face(face, blinking || button_held, self.angry);
@@ -101,9 +129,30 @@ impl StatusBar {
         }
         renderer.draw(DrawShape::image(rect.x + FACE_OFFSET.x, rect.y + FACE_OFFSET.y, face, None));
         renderer.draw(DrawShape::nineslice(rect, source));
+        if button_state.released() {
+            renderer.sound_player_mut().play(SoundEffect::Click);
+        }
         button_state.released()
     }
 
+    // Switches what tapping does, as there's no right click
+    fn flag_mode_button(&mut self, x: Align, y: Align, state: &mut State, renderer: &mut Renderer) {
+        let size = Vec2::splat(MINEFIELD_TILE_SIZE as f32 + 4.0);
+        let rect = aligned_rect(x, y, size.x, size.y);
+        let button_state = state.button_state(hash_string(&"flag_mode".to_owned()), state.mouse_in_rect(rect), false, true);
+        if button_state.released() {
+            self.flag_mode = !self.flag_mode;
+            renderer.sound_player_mut().play(SoundEffect::Click);
+        }
+
+        let (offset, source, _) = renderer.style().button(&button_state);
+        let rect = rect.offset(offset);
+        // A flag when flagging, and a tile being dug when digging
+        let icon = renderer.style().minefield_tile(if self.flag_mode { 4 } else { 1 });
+        renderer.draw(DrawShape::image(rect.x + 2.0, rect.y + 2.0, icon, None));
+        renderer.draw(DrawShape::nineslice(rect, source));
+    }
+
     fn bomb_counter(game: &Minesweeper, x: Align, y: Align, renderer: &mut Renderer) {
         let value = game.flags_left();
         // Calculate the minimum number of digits needed to display the bomb count (always being 2 or larger for style purposes: 3)
@@ -131,7 +180,7 @@ impl StatusBar {
                     _    => CounterDigit::Empty,
                 }
             ))
-            .map(|(i, digit)| (i, bomb_counter_digit(digit)))
+            .map(|(i, digit)| (i, renderer.style().bomb_counter_digit(digit)))
             // Render the digits in reverse order so they appear the right way around
             .map(|(i, digit_rect)| DrawShape::image(
                 rect.x + offset.x + (digit_rect.w + gap) * (digits - i - 1) as f32,
@@ -139,15 +188,36 @@ impl StatusBar {
                 digit_rect,
                 None
             ))
-            .chain(std::iter::once(DrawShape::nineslice(rect, background)));
+            .chain(std::iter::once(DrawShape::nineslice(rect, background)))
+            .collect::<Vec<_>>();
 
-        renderer.draw_iter(draw_shapes);
+        renderer.draw_iter(draw_shapes.into_iter());
+
+        if shows_lives(game) {
+            let color = renderer.style().text();
+            let lives = renderer.lang().plural("status.lives", game.lives_left(), &[]);
+            elements::text(lives, None, color, Align::Mid(rect.x + rect.w / 2.0), Align::Beg(rect.bottom() + 1.0), renderer);
+        }
     }
     
-    fn timer(timer: Option<f32>, x: Align, y: Align, renderer: &mut Renderer) {
+    fn timer(clock: Clock, x: Align, y: Align, renderer: &mut Renderer) {
         let size = TIMER_SIZE;
         let rect = aligned_rect(x, y, size.x, size.y);
 
+        // Count down if there's a limit, rounding up so it hits zero right as the time runs out
+        let (timer, warning) = match clock.limit {
+            Some(limit) => {
+                let remaining = (limit - clock.time.unwrap_or(0.0)).max(0.0);
+                let warning = clock.time.is_some() && remaining < TIMER_WARNING_TIME && (remaining == 0.0 || remaining.fract() > 0.5);
+                (Some(remaining.ceil()), warning)
+            }
+            None => (clock.time, false),
+        };
+        let color = match warning {
+            true  => Some(TIMER_WARNING),
+            false => None,
+        };
+
         let (digits, colon): ([Option<u32>; 4], bool) = if let Some(time) = timer {
             let seconds = (time as usize).min(60*100-1).try_into().unwrap_or(u32::MAX);
             let digits = [
@@ -167,10 +237,22 @@ impl StatusBar {
 
         let draw_shapes = digits.iter()
             .zip(digits_x)
-            .map(|(&digit, along)| DrawShape::image(rect.x + along,    rect.y + TIMER_DIGIT_Y, timer_digit(digit), None))
-            .chain(std::iter::once(DrawShape::image(rect.x + colon_x,  rect.y + TIMER_DIGIT_Y, timer_colon(colon), None)))
-            .chain(std::iter::once(DrawShape::nineslice(rect, renderer.style().timer_background())));
+            .map(|(&digit, along)| DrawShape::image(rect.x + along,    rect.y + TIMER_DIGIT_Y, renderer.style().timer_digit(digit), color))
+            .chain(std::iter::once(DrawShape::image(rect.x + colon_x,  rect.y + TIMER_DIGIT_Y, renderer.style().timer_colon(colon), color)))
+            .chain(std::iter::once(DrawShape::nineslice(rect, renderer.style().timer_background())))
+            .collect::<Vec<_>>();
+
+        renderer.draw_iter(draw_shapes.into_iter());
 
-        renderer.draw_iter(draw_shapes);
+        // Time attack's score goes just below
+        if let Some(boards_cleared) = clock.boards_cleared {
+            let color = renderer.style().text();
+            elements::text(format!("x{}", boards_cleared), None, color, Align::Mid(rect.x + rect.w / 2.0), Align::Beg(rect.bottom() + 1.0), renderer);
+        }
     }
-}
\ No newline at end of file
+}
+
+// How many lives are left goes under the bomb counter, if there's more than the usual one (and not so many it doesn't matter)
+fn shows_lives(game: &Minesweeper) -> bool {
+    (2..INFINITE_LIVES).contains(&game.lives())
+}
@@ -22,11 +22,14 @@ impl Round for Rect {
 }
 
 pub mod state;
+pub mod bindings;
+pub mod menu;
 pub mod menubar;
 pub mod popups;
 pub mod minesweeper_element;
 pub mod elements;
 pub mod renderer;
+pub mod shortcuts;
 
 pub struct Ui {
     pub state: State,
@@ -1,14 +1,15 @@
 use macroquad::{input::MouseButton, math::{vec2, Rect, Vec2}, miniquad::window::order_quit};
 
-use crate::minesweeper::{Difficulty, MAX_HEIGHT, MAX_WIDTH, MIN_HEIGHT, MIN_WIDTH};
+use crate::{daily, lang::Lang, ui::minesweeper_element::GameMode, minesweeper::{Difficulty, MAX_HEIGHT, MAX_WIDTH, MIN_HEIGHT, MIN_WIDTH}, settings::Settings, stats::{difficulty_name, format_time, today, DailyResult, Stats, Summary}};
 
-use super::{elements::{self, Align}, hash_string, menubar::Menubar, minesweeper_element::MinesweeperElement, renderer::{style::SHADOW, DrawShape, Renderer}, state::{ButtonState, Id, State}};
+use super::{bindings::{self, Action, Bindings, Input, Preset}, elements::{self, Align}, hash_string, menubar::Menubar, minesweeper_element::MinesweeperElement, renderer::{skin, sound::{SoundCategory, SoundEffect, Volume}, style::{FaceType, SHADOW}, DrawShape, Renderer}, state::{ButtonState, Id, SelectedItem, State}};
 
 #[derive(Default)]
 pub struct Popups {
     popups: Vec<Popup>,
     return_values: Vec<PopupReturn>,
     drag_offset: Vec2,
+    changed_settings: Option<Settings>,
 }
 
 impl Popups {
@@ -22,7 +23,10 @@ impl Popups {
         let mut close = None; 
         let mut front = None; 
 
+        let front_index = self.popups.len().saturating_sub(1);
         for (i, popup) in self.popups.iter_mut().enumerate().rev() {
+            // Only the front popup can be tabbed around and closed with escape
+            state.focus_scope = i == front_index;
             let (action, return_value) = popup.update(&mut self.drag_offset, state, menubar, renderer);
             match action {
                 PopupAction::Close => close = Some(i),
@@ -33,6 +37,7 @@ impl Popups {
                 self.return_values.push(r);
             }
         }
+        state.focus_scope = false;
 
         if let Some(close) = close {
             self.popups.remove(close);
@@ -43,28 +48,98 @@ impl Popups {
         }
     }
 
-    pub fn handle_returns(&mut self, minesweeper_element: &mut MinesweeperElement) {
-        for return_value in &mut self.return_values {
+    pub fn handle_returns(&mut self, minesweeper_element: &mut MinesweeperElement, stats: &mut Stats, state: &State) {
+        for return_value in std::mem::take(&mut self.return_values) {
             match return_value {
-                PopupReturn::NewGame { difficulty } => minesweeper_element.new_game(*difficulty),
+                PopupReturn::NewGame { difficulty, daily: false } => minesweeper_element.new_game(difficulty),
+                PopupReturn::NewGame { daily: true, .. }          => minesweeper_element.new_daily(&today()),
                 PopupReturn::Exit                   => order_quit(),
+                PopupReturn::ConfirmResetStats      => self.add(PopupKind::ResetStats, state),
+                PopupReturn::ResetStats             => {
+                    stats.reset();
+                    self.refresh_statistics(stats);
+                }
+                PopupReturn::SetName { record, name } => {
+                    stats.set_name(record, name);
+                    self.refresh_statistics(stats);
+                }
+                PopupReturn::PickStatistics => self.refresh_statistics(stats),
+                PopupReturn::Settings(settings) => self.changed_settings = Some(settings),
+                PopupReturn::EditControls(settings) => self.add(PopupKind::controls(&settings), state),
             }
         }
     }
+
+    // Makes sure the statistics popup (if there is one) is showing the latest numbers
+    pub fn refresh_statistics(&mut self, stats: &Stats) {
+        for popup in &mut self.popups {
+            if let PopupKind::Statistics { difficulty, mode, summary, .. } = &mut popup.kind {
+                *summary = stats.summary(*difficulty, *mode);
+            }
+        }
+    }
+
+    // Makes sure the settings popup (if there is one) starts from the settings as they are now,
+    // as they can also be changed from the menus
+    pub fn refresh_settings(&mut self, settings: &Settings) {
+        for popup in &mut self.popups {
+            if let PopupKind::Settings { settings: s, .. } | PopupKind::Controls { settings: s, .. } = &mut popup.kind {
+                *s = settings.clone();
+            }
+        }
+    }
+
+    // Whether the controls popup is waiting for something to bind, in which case the shortcuts shouldn't do anything
+    pub fn listening(&self) -> bool {
+        self.popups.iter().any(|p| matches!(p.kind, PopupKind::Controls { listening: Some(_), .. }))
+    }
+
+    // Whatever was changed in the settings popup, which need applying to the ui
+    pub fn changed_settings(&mut self) -> Option<Settings> {
+        self.changed_settings.take()
+    }
 }
 
 pub enum PopupKind {
-    NewGame { difficulty: Difficulty },
+    NewGame { difficulty: Difficulty, daily: bool },
     Custom { width: String, height: String, bomb_count: String },
     About,
     Hint,
-    Win,
+    Win { time: f32, record: u64, rank: Option<usize>, name: String, entered: bool },
     Exit,
+    // difficulties is everything that can be picked, which is whatever's been played along with the standard ones
+    Statistics { difficulties: Vec<Difficulty>, difficulty: Difficulty, mode: GameMode, summary: Summary },
+    ResetStats,
+    Daily { date: String, result: DailyResult, counted: bool, streak: usize },
+    TimeUp { boards_cleared: Option<usize>, rank: Option<usize> },
+    Settings { tab: SettingsTab, settings: Settings, skins: Vec<(String, String)>, languages: Vec<(String, String)> },
+    // listening is the action waiting for something to be bound to it, which only starts once whatever
+    // pressed the add button has been let go of (ready), and held is the most that's been held down since
+    Controls { settings: Settings, listening: Option<Action>, ready: bool, held: Option<Input> },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SettingsTab {
+    Gameplay, Display, Sound, Controls,
+}
+
+impl SettingsTab {
+    const ALL: [SettingsTab; 4] = [SettingsTab::Gameplay, SettingsTab::Display, SettingsTab::Sound, SettingsTab::Controls];
+
+    fn label_key(&self) -> &'static str {match self {
+        SettingsTab::Gameplay => "popup.settings.gameplay",
+        SettingsTab::Display  => "popup.settings.display",
+        SettingsTab::Sound    => "popup.settings.sound",
+        SettingsTab::Controls => "popup.settings.controls",
+    }}
 }
 
 impl PopupKind {
     pub fn new_game(difficulty: Difficulty) -> Self {
-        Self::NewGame { difficulty }
+        Self::NewGame { difficulty, daily: false }
+    }
+    pub fn new_daily() -> Self {
+        Self::NewGame { difficulty: daily::DAILY_DIFFICULTY, daily: true }
     }
     pub fn custom(difficulty: Option<Difficulty>) -> Self {
         let (width, height, bomb_count) = match difficulty {
@@ -76,11 +151,34 @@ impl PopupKind {
         };
         Self::Custom { width, height, bomb_count }
     }
+    pub fn win(time: f32, record: u64, rank: Option<usize>) -> Self {
+        Self::Win { time, record, rank, name: String::new(), entered: false }
+    }
+    pub fn statistics(difficulty: Difficulty, mode: GameMode, stats: &Stats) -> Self {
+        let mut difficulties = stats.difficulties();
+        if !difficulties.contains(&difficulty) {
+            difficulties.push(difficulty);
+        }
+        Self::Statistics { difficulties, difficulty, mode, summary: stats.summary(difficulty, mode) }
+    }
+    pub fn settings(settings: &Settings) -> Self {
+        Self::Settings { tab: SettingsTab::Gameplay, settings: settings.clone(), skins: skin::list(), languages: Lang::list() }
+    }
+    pub fn controls(settings: &Settings) -> Self {
+        Self::Controls { settings: settings.clone(), listening: None, ready: false, held: None }
+    }
 }
 
 pub enum PopupReturn {
-    NewGame { difficulty: Difficulty },
+    NewGame { difficulty: Difficulty, daily: bool },
     Exit,
+    ConfirmResetStats,
+    ResetStats,
+    SetName { record: u64, name: String },
+    // The statistics popup has had a different difficulty or mode picked
+    PickStatistics,
+    Settings(Settings),
+    EditControls(Settings),
 }
 
 pub enum PopupAction {
@@ -90,43 +188,321 @@ pub enum PopupAction {
 }
 
 pub struct Popup {
+    // Where its centre is as a fraction of the screen, so it stays in the same sort of place when the window's resized or rescaled
+    anchor: Vec2,
     pos: Vec2,
     size: Vec2,
-    title: String,
+    dragging: bool,
+    // The size it'd be in English, which it never gets smaller than so English looks just how it was laid out
+    min_size: Vec2,
     kind: PopupKind,
     id: Id,
 }
 
+// Space around the edges of the body, and between buttons next to each other
+const MARGIN: f32 = 3.0;
+const BUTTON_GAP: f32 = 3.0;
+const NAME_FIELD_WIDTH: f32 = 60.0;
+const NUMBER_FIELD_WIDTH: f32 = 41.0;
+// Each setting is a row with its label on the left and whatever changes it on the right
+const SETTINGS_ROW_HEIGHT:   f32 = 10.0;
+const SETTINGS_WIDGET_WIDTH: f32 = 60.0;
+const SETTINGS_TOP:          f32 = 16.0;
+const SETTINGS_ROWS:         usize = 7;
+const SETTINGS_LABELS: [&str; 15] = [
+    "popup.settings.mode", "menu.auto_flag", "menu.auto_open",
+    "popup.settings.skin", "popup.settings.face", "popup.settings.language", "menu.number_cues", "menu.screen_shake", "popup.settings.auto_scale", "menu.scale",
+    "menu.mute", "sound.master", "sound.game", "sound.jingles", "sound.interface",
+];
+// The controls that can't be changed, and what does them. They go under the ones that can
+const CONTROLS: [(&str, &str); 4] = [
+    ("popup.settings.move",  "popup.settings.move_keys"),
+    ("popup.settings.next",  "popup.settings.next_keys"),
+    ("popup.settings.zoom",  "popup.settings.zoom_keys"),
+    ("popup.settings.pan",   "popup.settings.pan_keys"),
+];
+// Each action in the controls popup has its name and buttons on one line, and what it's bound to on the next
+const CONTROLS_BLOCK_HEIGHT: f32 = 22.0;
+const CONTROLS_PRESETS_GAP:  f32 = 9.0;
+// Links in the about text, which are found in whatever language it's in
+const ABOUT_LINKS: [(&str, &str); 3] = [
+    ("jumbledFox", "https://jumbledFox.github.io"),
+    ("Macroquad",  "https://github.com/not-fl3/macroquad"),
+    ("Github",     "https://github.com/jumbledfox/minesweeper"),
+];
+
+fn text_size(text: &String, renderer: &Renderer) -> Vec2 {
+    renderer.text_renderer.text_size(text, None)
+}
+// The same as the size of elements::button_text
+fn button_size(text: &String, renderer: &Renderer) -> Vec2 {
+    text_size(text, renderer) + vec2(6.0, 4.0)
+}
+// How wide a row of buttons is, with gaps between them
+fn buttons_width(texts: &[&String], renderer: &Renderer) -> f32 {
+    texts.iter().map(|t| button_size(t, renderer).x + BUTTON_GAP).sum::<f32>() - BUTTON_GAP
+}
+// The size of a body with some text at the top and a row of buttons at the bottom
+fn message_size(message: &String, buttons: &[&String], renderer: &Renderer) -> Vec2 {
+    let message = text_size(message, renderer);
+    let button_height = button_size(&String::new(), renderer).y;
+    vec2(message.x.max(buttons_width(buttons, renderer)), message.y + 2.0 + button_height) + MARGIN * 2.0
+}
+
+// The choices in the statistics' selects: difficulties and then modes
+fn statistics_options(lang: &Lang, difficulties: &[Difficulty]) -> [Vec<String>; 2] {
+    [
+        difficulties.iter().map(|d| difficulty_name(*d, lang)).collect(),
+        GameMode::ALL.iter().map(|m| lang.get(m.label_key())).collect(),
+    ]
+}
+
+// A select is as wide as its widest option
+fn select_width(options: &[String], renderer: &Renderer) -> f32 {
+    options.iter().map(|o| text_size(o, renderer).x + 12.0).fold(0.0, f32::max)
+}
+
+// What goes in each column of the statistics, played and streak and then the best times
+fn statistics_columns(lang: &Lang, mode: GameMode, summary: &Summary) -> (Vec<String>, Vec<String>) {
+    let best: Vec<String> = summary.best.iter().enumerate().map(|(i, record)| {
+        let value = if mode == GameMode::TimeAttack { format!("x{}", record.score) } else { format_time(record.time) };
+        format!("{}. {}", i + 1, value)
+    }).collect();
+    let (best_left, best_right) = best.split_at(best.len().min(5));
+    // Nobody wins in time attack, so there's no point showing how often or how many in a row
+    let (won, streak, best_streak) = match mode {
+        GameMode::TimeAttack => (String::new(), String::new(), String::new()),
+        _ => (
+            lang.format("popup.statistics.won",    &[&summary.win_percentage()]),
+            lang.format("popup.statistics.streak", &[&summary.current_streak]),
+            lang.format("popup.statistics.best",   &[&summary.best_streak]),
+        ),
+    };
+    let left = [
+        lang.format("popup.statistics.played", &[&summary.played]),
+        streak,
+    ].into_iter().chain(best_left.iter().cloned()).collect();
+    let right = [won, best_streak].into_iter().chain(best_right.iter().cloned()).collect();
+    (left, right)
+}
+
+// Where the right column starts, after everything in the left one
+fn statistics_right_x(left: &[String], renderer: &Renderer) -> f32 {
+    left.iter().map(|t| text_size(t, renderer).x + 6.0).fold(63.0, f32::max)
+}
+
+// The labels are in a column to the left of the number fields
+fn custom_label_width(renderer: &Renderer) -> f32 {
+    ["popup.custom.width", "popup.custom.height", "popup.custom.bombs"].iter()
+        .map(|key| text_size(&renderer.lang().get(key), renderer).x + 4.0)
+        .fold(34.0, f32::max)
+}
+
+// The choices in each of the settings' selects: modes, skins, faces and languages
+fn settings_options(lang: &Lang, skins: &[(String, String)], languages: &[(String, String)]) -> [Vec<String>; 4] {
+    [
+        GameMode::ALL.iter().map(|m| lang.get(m.label_key())).collect(),
+        // Built-in skins have their names translated
+        skins.iter().map(|(name, label)| lang.try_get(&format!("skin.{}", name)).unwrap_or(label).to_owned()).collect(),
+        FaceType::ALL.iter().map(|f| lang.get(&format!("face.{}", f.name()))).collect(),
+        // Languages are always in their own language, so they can be found whatever the current one is
+        languages.iter().map(|(_, name)| name.clone()).collect(),
+    ]
+}
+
+// How wide the labels and the widgets next to them are, the same on every tab so switching between them doesn't change the size
+fn settings_columns(options: &[Vec<String>], bindings: &Bindings, renderer: &Renderer) -> (f32, f32) {
+    let width = |text: &String| text_size(text, renderer).x;
+    let labels = SETTINGS_LABELS.iter().copied().chain(Action::ALL.iter().map(Action::label_key)).chain(CONTROLS.iter().map(|(label, _)| *label))
+        .map(|key| width(&renderer.lang().get(key)) + 6.0)
+        .fold(0.0, f32::max);
+    let widgets = options.iter().flatten()
+        .map(|option| width(option) + 12.0)
+        .chain(Action::ALL.iter().map(|a| width(&bindings.label(*a, renderer.lang()))))
+        .chain(CONTROLS.iter().map(|(_, keys)| width(&renderer.lang().get(keys))))
+        .fold(SETTINGS_WIDGET_WIDTH, f32::max);
+    (labels, widgets)
+}
+
+impl PopupKind {
+    fn title(&self, lang: &Lang) -> String {
+        lang.get(match self {
+            PopupKind::NewGame{..}    => "popup.new_game.title",
+            PopupKind::Custom{..}     => "popup.custom.title",
+            PopupKind::About          => "popup.about.title",
+            PopupKind::Hint           => "popup.hint.title",
+            PopupKind::Win{..}        => "popup.win.title",
+            PopupKind::Exit           => "popup.exit.title",
+            PopupKind::Statistics{..} => "popup.statistics.title",
+            PopupKind::ResetStats     => "popup.reset_stats.title",
+            PopupKind::Daily{..}      => "popup.daily.title",
+            PopupKind::TimeUp{..}     => "popup.time_up.title",
+            PopupKind::Settings{..}   => "popup.settings.title",
+            PopupKind::Controls{..}   => "popup.controls.title",
+        })
+    }
+
+    // The text at the top of the body, for the popups that have one
+    fn message(&self, lang: &Lang) -> String {
+        match self {
+            PopupKind::NewGame{..} => lang.get("popup.new_game.message"),
+            PopupKind::Exit        => lang.get("popup.exit.message"),
+            PopupKind::ResetStats  => lang.get("popup.reset_stats.message"),
+            PopupKind::Hint        => lang.get("popup.hint.message"),
+            PopupKind::About       => lang.get("popup.about.text"),
+            PopupKind::Win { time, .. } => lang.format("popup.win.message", &[&format_time(*time)]),
+            PopupKind::Daily { date, result, .. } => match result.won {
+                true  => lang.format("popup.daily.won",  &[date, &format_time(result.time)]),
+                false => lang.format("popup.daily.lost", &[date]),
+            },
+            PopupKind::TimeUp { boards_cleared, rank } => match (boards_cleared, rank) {
+                (Some(b), Some(r)) => lang.plural("popup.time_up.cleared", *b, &[]) + "\n" + &lang.format("popup.time_up.rank", &[r]),
+                (Some(b), None)    => lang.plural("popup.time_up.cleared", *b, &[]),
+                (None, _)          => lang.get("popup.time_up.ran_out"),
+            },
+            PopupKind::Custom{..} | PopupKind::Statistics{..} | PopupKind::Settings{..} | PopupKind::Controls{..} => String::new(),
+        }
+    }
+
+    // The line under the message when you've won
+    fn win_line(&self, lang: &Lang) -> String {
+        match self {
+            PopupKind::Win { rank: Some(_), entered: false, .. } => lang.get("popup.win.new_best"),
+            PopupKind::Win { rank: Some(rank), .. }              => lang.format("popup.win.placed", &[rank]),
+            _ => String::new(),
+        }
+    }
+}
+
 impl Popup {
     pub fn new(kind: PopupKind, state: &State) -> Popup {
-        let (title, size) = match kind {
-            PopupKind::NewGame{..} => ("New game", vec2( 90.0, 46.0)),
-            PopupKind::Custom{..}  => ("Custom",   vec2( 78.0, 58.0)),
-            PopupKind::About       => ("About",    vec2(100.0, 70.0)),
-            PopupKind::Hint        => ("Hint",     vec2( 60.0, 40.0)),
-            PopupKind::Win         => ("You win!", vec2( 70.0, 34.0)),
-            PopupKind::Exit        => ("Exit",     vec2( 70.0, 40.0)),
+        let size = match kind {
+            PopupKind::NewGame{..}         => vec2( 90.0, 46.0),
+            PopupKind::Custom{..}          => vec2( 78.0, 58.0),
+            PopupKind::About               => vec2(100.0, 70.0),
+            PopupKind::Hint                => vec2( 60.0, 40.0),
+            PopupKind::Win{rank: None, ..} => vec2( 80.0, 41.0),
+            PopupKind::Win{..}             => vec2( 96.0, 58.0),
+            PopupKind::Exit                => vec2( 70.0, 40.0),
+            PopupKind::Statistics{..}      => vec2(128.0, 100.0),
+            PopupKind::ResetStats          => vec2( 90.0, 46.0),
+            PopupKind::Daily{..}           => vec2(112.0, 62.0),
+            PopupKind::TimeUp{..}          => vec2( 84.0, 41.0),
+            PopupKind::Settings{..}        => vec2(130.0, 110.0),
+            PopupKind::Controls{..}        => vec2(110.0, 110.0),
         };
         let pos = (state.screen_size() - size) / 2.0;
 
-        Popup { pos, size, title: title.to_owned(), kind, id: hash_string(&format!("popup!!{}", macroquad::miniquad::date::now())) }
+        Popup { anchor: vec2(0.5, 0.5), pos, size, dragging: false, min_size: size, kind, id: hash_string(&format!("popup!!{}", macroquad::miniquad::date::now())) }
+    }
+
+    // How big the body needs to be to fit everything in it
+    fn body_size(&self, renderer: &Renderer) -> Vec2 {
+        let lang = renderer.lang();
+        let message = self.kind.message(lang);
+        let width = |texts: &[String]| texts.iter().map(|t| text_size(t, renderer).x).fold(0.0, f32::max);
+        match &self.kind {
+            PopupKind::Exit       => message_size(&message, &[&lang.get("button.cancel"), &lang.get("popup.exit.button")], renderer),
+            PopupKind::NewGame{..}
+          | PopupKind::ResetStats => message_size(&message, &[&lang.get("button.cancel"), &lang.get("button.yes")], renderer),
+            PopupKind::Hint       => message_size(&message, &[&lang.get("popup.hint.button")], renderer),
+            PopupKind::TimeUp{..} => message_size(&message, &[&lang.get("popup.time_up.button")], renderer),
+            PopupKind::About      => text_size(&message, renderer) + MARGIN * 2.0,
+            PopupKind::Win { rank, entered, .. } => {
+                let buttons = match (rank, entered) {
+                    (Some(_), false) => NAME_FIELD_WIDTH.max(text_size(&lang.get("popup.win.name_hint"), renderer).x + 4.0)
+                        + BUTTON_GAP + button_size(&lang.get("button.submit"), renderer).x,
+                    _ => button_size(&lang.get("popup.win.button"), renderer).x,
+                };
+                let line = self.kind.win_line(lang);
+                let message = text_size(&message, renderer);
+                let line_height = if line.is_empty() { 0.0 } else { text_size(&line, renderer).y + 1.0 };
+                vec2(
+                    message.x.max(text_size(&line, renderer).x).max(buttons),
+                    message.y + line_height + 2.0 + button_size(&String::new(), renderer).y,
+                ) + MARGIN * 2.0
+            }
+            PopupKind::Daily { streak, .. } => {
+                let message = text_size(&message, renderer);
+                let streak = text_size(&lang.format("popup.daily.streak", &[streak]), renderer).x;
+                let buttons = buttons_width(&[&lang.get("popup.daily.copy_code"), &lang.get("button.close")], renderer);
+                let others = width(&[lang.get("popup.daily.not_counted"), "MSD-00000000-00:00.0-S000".to_owned()]);
+                vec2((message.x + BUTTON_GAP * 2.0 + streak).max(buttons).max(others), message.y + 27.0) + MARGIN * 2.0
+            }
+            PopupKind::Statistics { difficulties, mode, summary, .. } => {
+                let (left, right) = statistics_columns(lang, *mode, summary);
+                let right_x = statistics_right_x(&left, renderer);
+                let [difficulty_options, mode_options] = statistics_options(lang, difficulties);
+                let selects = select_width(&difficulty_options, renderer) + BUTTON_GAP + select_width(&mode_options, renderer);
+                let headings = width(&[lang.get("popup.statistics.best_times"), lang.get("popup.statistics.best_scores")]);
+                let buttons = buttons_width(&[&lang.get("popup.statistics.reset"), &lang.get("button.close")], renderer);
+                vec2((right_x + width(&right)).max(selects).max(headings).max(buttons), 0.0) + MARGIN * 2.0
+            }
+            PopupKind::Custom{..} => {
+                let buttons = buttons_width(&[&lang.get("button.cancel"), &lang.get("button.submit")], renderer);
+                vec2((custom_label_width(renderer) + NUMBER_FIELD_WIDTH).max(buttons), 0.0) + MARGIN * 2.0
+            }
+            PopupKind::Settings { settings, skins, languages, .. } => {
+                let (labels, widgets) = settings_columns(&settings_options(lang, skins, languages), &settings.bindings, renderer);
+                let tabs = SettingsTab::ALL.iter().map(|t| button_size(&lang.get(t.label_key()), renderer).x).sum::<f32>();
+                let height = SETTINGS_TOP + SETTINGS_ROWS as f32 * SETTINGS_ROW_HEIGHT + 2.0 + button_size(&String::new(), renderer).y;
+                vec2((labels + widgets).max(tabs), height) + MARGIN * 2.0
+            }
+            PopupKind::Controls { settings, .. } => {
+                let buttons = buttons_width(&[&lang.get("popup.controls.clear"), &lang.get("popup.controls.add")], renderer);
+                let labels = Action::ALL.iter().map(|a| text_size(&lang.get(a.label_key()), renderer).x + 4.0 + buttons).fold(0.0, f32::max);
+                let bound = Action::ALL.iter().map(|a| settings.bindings.label(*a, lang)).chain([lang.get("popup.controls.listening")]).collect::<Vec<_>>();
+                let presets = Preset::ALL.iter().map(|p| lang.get(p.label_key())).collect::<Vec<_>>();
+                let presets = buttons_width(&presets.iter().collect::<Vec<_>>(), renderer);
+                let button_height = button_size(&String::new(), renderer).y;
+                let height = Action::ALL.len() as f32 * CONTROLS_BLOCK_HEIGHT + CONTROLS_PRESETS_GAP + button_height * 2.0 + 4.0;
+                vec2(labels.max(width(&bound) + 4.0).max(presets), height) + MARGIN * 2.0
+            }
+        }
     }
 
     pub fn update(&mut self, drag_offset: &mut Vec2, state: &mut State, menubar: &Menubar, renderer: &mut Renderer) -> (PopupAction, Option<PopupReturn>) {
-        
-        let titlebar_height = renderer.text_renderer.text_size(&self.title, None).y + 3.0;
-        self.pos = self.pos
-            .min(state.screen_size() - titlebar_height)
-            .max(vec2(-self.size.x + titlebar_height, menubar.height()))
-            .round();
+        let title = self.kind.title(renderer.lang());
+        let titlebar_height = text_size(&title, renderer).y + 3.0;
+
+        // Fitting everything in, staying centered on the same place if that changes the size
+        let body_size = self.body_size(renderer);
+        self.size = vec2(
+            body_size.x.max(text_size(&title, renderer).x + 13.0),
+            body_size.y + titlebar_height,
+        ).max(self.min_size);
+
+        // It has to be all on the screen, apart from while it's being dragged when only the titlebar does
+        let screen = state.screen_size();
+        let centered = self.anchor * screen - self.size / 2.0;
+        let on_screen = centered.min(screen - self.size).max(vec2(0.0, menubar.height()));
+        let dragging = state.active_item == self.id;
+        self.pos = match dragging {
+            true  => centered.min(screen - titlebar_height).max(vec2(-self.size.x + titlebar_height, menubar.height())),
+            false => on_screen,
+        }.round();
+        // Letting go of it off the screen brings it back
+        if self.dragging && !dragging {
+            self.anchor = (on_screen + self.size / 2.0) / screen;
+        }
+        self.dragging = dragging;
 
         let title_rect = Rect::new(self.pos.x, self.pos.y,                self.size.x, titlebar_height);
         let body_rect  = Rect::new(self.pos.x, self.pos.y + title_rect.h, self.size.x, self.size.y - title_rect.h);
+        // Buttons go along the bottom, each one to the left of the one after it
+        let bottom = Align::End(body_rect.bottom() - MARGIN);
+        let right  = body_rect.right() - MARGIN;
+        let left_of = |x: f32, text: &String, renderer: &Renderer| x - button_size(text, renderer).x - BUTTON_GAP;
 
         let id = self.id;
         let mut id_add = 1;
         
         let mut return_value = None;
+        // Nothing else gets the mouse or keyboard while the controls popup is waiting for something to bind
+        if state.focus_scope && matches!(self.kind, PopupKind::Controls { listening: Some(_), .. }) {
+            state.hot_item = SelectedItem::Unavailable;
+            state.capture_keyboard();
+        }
         let mut close = Popup::close_button(id.wrapping_add(id_add), title_rect, state, renderer);
 
         let active_before = state.active_item;
@@ -142,60 +518,174 @@ impl Popup {
             *id_add += 1;
             elements::button_text(self.id.wrapping_add(*id_add), text, x, y, disabled, state, renderer)
         };
+        // The button that enter presses, unless something that's focused wants it
+        let default_button = |text: String, x: Align, y: Align, disabled: bool, state: &mut State, renderer: &mut Renderer, id_add: &mut Id| {
+            let button_state = button(text, x, y, disabled, state, renderer, id_add);
+            match !disabled && !button_state.released() && state.default_pressed() {
+                true  => { renderer.sound_player_mut().play(SoundEffect::Click); ButtonState::Released }
+                false => button_state,
+            }
+        };
         let number_field = |number: &mut String, hint: String, x: Align, y: Align, w: f32, state: &mut State, renderer: &mut Renderer, id_add: &mut Id| {
             *id_add += 1;
             elements::number_field(self.id.wrapping_add(*id_add), number, 7, hint, x, y, w, state, renderer)
         }; 
+        let text_field = |text: &mut String, hint: String, x: Align, y: Align, w: f32, state: &mut State, renderer: &mut Renderer, id_add: &mut Id| {
+            *id_add += 1;
+            elements::text_field(self.id.wrapping_add(*id_add), text, 12, hint, |_| true, x, y, w, state, renderer)
+        };
+        let checkbox = |value: &mut bool, x: Align, y: Align, state: &mut State, renderer: &mut Renderer, id_add: &mut Id| {
+            *id_add += 1;
+            elements::checkbox(self.id.wrapping_add(*id_add), value, x, y, state, renderer)
+        };
+        let slider = |value: &mut f32, (min, max, step): (f32, f32, f32), x: Align, y: Align, w: f32, state: &mut State, renderer: &mut Renderer, id_add: &mut Id| {
+            *id_add += 1;
+            elements::slider(self.id.wrapping_add(*id_add), value, min, max, step, x, y, w, state, renderer)
+        };
+        let select = |options: &[String], selected: &mut usize, x: Align, y: Align, w: f32, state: &mut State, renderer: &mut Renderer, id_add: &mut Id| {
+            *id_add += 1;
+            elements::select(self.id.wrapping_add(*id_add), options, selected, x, y, w, state, renderer)
+        };
+        let lang = |key: &str, renderer: &Renderer| renderer.lang().get(key);
+
+        let message  = self.kind.message(renderer.lang());
+        let win_line = self.kind.win_line(renderer.lang());
 
         match &mut self.kind {
             PopupKind::Exit => {
-                text("Are you sure you\nwant to exit?".to_owned(), Align::Beg(body_rect.x+3.0), Align::Beg(body_rect.y+3.0), renderer);
-                if button("Exit".to_owned(), Align::End(body_rect.right() - 3.0), Align::End(body_rect.bottom() - 3.0), false, state, renderer, &mut id_add).released() {
+                text(message, Align::Beg(body_rect.x+3.0), Align::Beg(body_rect.y+3.0), renderer);
+                let exit = lang("popup.exit.button", renderer);
+                let cancel_x = left_of(right, &exit, renderer);
+                if default_button(exit, Align::End(right), bottom, false, state, renderer, &mut id_add).released() {
                     return_value = Some(PopupReturn::Exit);
                     close = true;
                 }
-                close = close | button("Cancel".to_owned(), Align::End(body_rect.right() - 25.0), Align::End(body_rect.bottom() - 3.0), false, state, renderer, &mut id_add).released();
+                close |= button(lang("button.cancel", renderer), Align::End(cancel_x), bottom, false, state, renderer, &mut id_add).released();
+            }
+            PopupKind::NewGame { difficulty, daily } => {
+                text(message, Align::Beg(body_rect.x+3.0), Align::Beg(body_rect.y+3.0), renderer);
+                let yes = lang("button.yes", renderer);
+                let cancel_x = left_of(right, &yes, renderer);
+                if default_button(yes, Align::End(right), bottom, false, state, renderer, &mut id_add).released() {
+                    return_value = Some(PopupReturn::NewGame { difficulty: *difficulty, daily: *daily });
+                    close = true;
+                }
+                close |= button(lang("button.cancel", renderer), Align::End(cancel_x), bottom, false, state, renderer, &mut id_add).released();
             }
-            PopupKind::NewGame { difficulty } => {
-                text("Are you sure you want\nto start a new game?".to_owned(), Align::Beg(body_rect.x+3.0), Align::Beg(body_rect.y+3.0), renderer);
-                if button("Yes".to_owned(), Align::End(body_rect.right() - 3.0), Align::End(body_rect.bottom() - 3.0), false, state, renderer, &mut id_add).released() {
-                    return_value = Some(PopupReturn::NewGame { difficulty: *difficulty });
+            PopupKind::Statistics { difficulties, difficulty, mode, summary } => {
+                let (left_column, right_column) = statistics_columns(renderer.lang(), *mode, summary);
+                let left  = Align::Beg(body_rect.x + 3.0);
+                let right_x = Align::Beg(body_rect.x + 3.0 + statistics_right_x(&left_column, renderer));
+                let heading = if *mode == GameMode::TimeAttack { "popup.statistics.best_scores" } else { "popup.statistics.best_times" };
+
+                // The selects go first so their lists are drawn over everything else
+                let [difficulty_options, mode_options] = statistics_options(renderer.lang(), difficulties);
+                let difficulty_width = select_width(&difficulty_options, renderer);
+                let mode_width = select_width(&mode_options, renderer);
+                let mut difficulty_index = difficulties.iter().position(|d| d == difficulty).unwrap_or(0);
+                let mut mode_index = GameMode::ALL.iter().position(|m| m == mode).unwrap_or(0);
+                let mode_x = Align::Beg(body_rect.x + 3.0 + difficulty_width + BUTTON_GAP);
+                let picked_difficulty = select(&difficulty_options, &mut difficulty_index, left,   Align::Beg(body_rect.y + 2.0), difficulty_width, state, renderer, &mut id_add);
+                let picked_mode       = select(&mode_options,       &mut mode_index,       mode_x, Align::Beg(body_rect.y + 2.0), mode_width,       state, renderer, &mut id_add);
+                if picked_difficulty || picked_mode {
+                    *difficulty = difficulties[difficulty_index];
+                    *mode = GameMode::ALL[mode_index];
+                    return_value = Some(PopupReturn::PickStatistics);
+                }
+
+                text(lang(heading, renderer), left, Align::Beg(body_rect.y + 32.0), renderer);
+                if summary.best.is_empty() {
+                    text(lang("popup.statistics.nothing_yet", renderer), left, Align::Beg(body_rect.y + 41.0), renderer);
+                }
+
+                // Played and streaks, then two columns of five best times
+                for (column, x) in [(left_column, left), (right_column, right_x)] {
+                    for (i, line) in column.into_iter().enumerate() {
+                        let y = match i {
+                            0 | 1 => body_rect.y + 14.0 + i as f32 * 7.0,
+                            _     => body_rect.y + 41.0 + (i - 2) as f32 * 7.0,
+                        };
+                        text(line, x, Align::Beg(y), renderer);
+                    }
+                }
+
+                if button(lang("popup.statistics.reset", renderer), Align::Beg(body_rect.x + 3.0), bottom, summary.played == 0, state, renderer, &mut id_add).released() {
+                    return_value = Some(PopupReturn::ConfirmResetStats);
+                }
+                close |= default_button(lang("button.close", renderer), Align::End(right), bottom, false, state, renderer, &mut id_add).released();
+            }
+            PopupKind::ResetStats => {
+                text(message, Align::Beg(body_rect.x+3.0), Align::Beg(body_rect.y+3.0), renderer);
+                let yes = lang("button.yes", renderer);
+                let cancel_x = left_of(right, &yes, renderer);
+                if default_button(yes, Align::End(right), bottom, false, state, renderer, &mut id_add).released() {
+                    return_value = Some(PopupReturn::ResetStats);
                     close = true;
                 }
-                close = close | button("Cancel".to_owned(), Align::End(body_rect.right() - 25.0), Align::End(body_rect.bottom() - 3.0), false, state, renderer, &mut id_add).released();
+                close |= button(lang("button.cancel", renderer), Align::End(cancel_x), bottom, false, state, renderer, &mut id_add).released();
+            }
+            PopupKind::Daily { date, result, counted, streak } => {
+                let message_height = text_size(&message, renderer).y;
+                text(message, Align::Beg(body_rect.x+3.0), Align::Beg(body_rect.y+3.0), renderer);
+                text(renderer.lang().format("popup.daily.streak", &[streak]), Align::End(body_rect.right()-3.0), Align::Beg(body_rect.y+3.0), renderer);
+                if !*counted {
+                    text(lang("popup.daily.not_counted", renderer), Align::Beg(body_rect.x+3.0), Align::Beg(body_rect.y+4.0+message_height), renderer);
+                }
+                let code = daily::share_code(date, *result, *streak);
+                text(code.clone(), Align::Beg(body_rect.x+3.0), Align::End(body_rect.bottom()-14.0), renderer);
+
+                if button(lang("popup.daily.copy_code", renderer), Align::Beg(body_rect.x+3.0), bottom, false, state, renderer, &mut id_add).released() {
+                    macroquad::miniquad::window::clipboard_set(&code);
+                }
+                close |= default_button(lang("button.close", renderer), Align::End(right), bottom, false, state, renderer, &mut id_add).released();
+            }
+            PopupKind::TimeUp { .. } => {
+                text(message, Align::Beg(body_rect.x+3.0), Align::Beg(body_rect.y+3.0), renderer);
+                close |= default_button(lang("popup.time_up.button", renderer), Align::End(right), bottom, false, state, renderer, &mut id_add).released();
             }
             PopupKind::Hint => {
-                text("You're on your\nown.".to_owned(),  Align::Beg(body_rect.x+3.0),       Align::Beg(body_rect.y+3.0), renderer);
-                close = close | button("Ah.".to_owned(), Align::End(body_rect.right()-3.0), Align::End(body_rect.bottom()-3.0), false, state, renderer, &mut id_add).released();
+                text(message, Align::Beg(body_rect.x+3.0), Align::Beg(body_rect.y+3.0), renderer);
+                close |= default_button(lang("popup.hint.button", renderer), Align::End(right), bottom, false, state, renderer, &mut id_add).released();
             }
-            PopupKind::Win => {
-                text("Congratulations!".to_owned(),  Align::Beg(body_rect.x+3.0),       Align::Beg(body_rect.y+3.0), renderer);
-                close = close | button("Yippee!".to_owned(), Align::End(body_rect.right()-3.0), Align::End(body_rect.bottom()-3.0), false, state, renderer, &mut id_add).released();
+            PopupKind::Win { record, rank, name, entered, .. } => {
+                let line_y = body_rect.y + 4.0 + text_size(&message, renderer).y;
+                text(message,  Align::Beg(body_rect.x+3.0), Align::Beg(body_rect.y+3.0), renderer);
+                text(win_line, Align::Beg(body_rect.x+3.0), Align::Beg(line_y), renderer);
+                match (*rank, *entered) {
+                    // A new best time that hasn't been named yet, ask for a name
+                    (Some(_), false) => {
+                        let (hint, submit) = (lang("popup.win.name_hint", renderer), lang("button.submit", renderer));
+                        let width = NAME_FIELD_WIDTH.max(text_size(&hint, renderer).x + 4.0);
+                        text_field(name, hint, Align::Beg(body_rect.x+3.0), bottom, width, state, renderer, &mut id_add);
+                        if default_button(submit, Align::End(right), bottom, name.trim().is_empty(), state, renderer, &mut id_add).released() {
+                            return_value = Some(PopupReturn::SetName { record: *record, name: name.trim().to_owned() });
+                            *entered = true;
+                        }
+                    }
+                    _ => {
+                        close |= default_button(lang("popup.win.button", renderer), Align::End(right), bottom, false, state, renderer, &mut id_add).released();
+                    }
+                }
             }
             PopupKind::About => {
-                url(
-                    "jumbledFox".to_owned(), "https://jumbledFox.github.io".to_owned(),          Align::Beg(body_rect.x+3.0),  Align::Beg(body_rect.y+10.0), 
-                    state, renderer, &mut id_add
-                );
-                url(
-                    "Macroquad".to_owned(), "https://github.com/not-fl3/macroquad".to_owned(),   Align::Beg(body_rect.x+24.0), Align::Beg(body_rect.y+31.0),
-                    state, renderer, &mut id_add
-                );
-                url(
-                    "Github".to_owned(), "https://github.com/jumbledfox/minesweeper".to_owned(), Align::Beg(body_rect.x+63.0), Align::Beg(body_rect.y+45.0),
-                    state, renderer, &mut id_add
-                );
-                text(
-                    "Minesweeper\njumbledFox - 2024\n\nMade with love in Rust, \nusing Macroquad.\n\nOpen source on Github!\nThanks for playing <3".to_owned(),
-                    Align::Beg(body_rect.x + 3.0), Align::Beg(body_rect.y + 3.0), renderer
-                )
+                // The links go on top of wherever their words are in the text
+                let lines: Vec<&str> = message.split('\n').collect();
+                let line_height = text_size(&String::new(), renderer).y;
+                for (word, link) in ABOUT_LINKS {
+                    let Some((i, start)) = lines.iter().enumerate().find_map(|(i, l)| l.find(word).map(|s| (i, s))) else { continue };
+                    let x = text_size(&lines[i][..start].to_owned(), renderer).x;
+                    let y = text_size(&lines[..=i].join("\n"), renderer).y - line_height;
+                    url(word.to_owned(), link.to_owned(), Align::Beg(body_rect.x + 3.0 + x), Align::Beg(body_rect.y + 3.0 + y), state, renderer, &mut id_add);
+                }
+                text(message, Align::Beg(body_rect.x + 3.0), Align::Beg(body_rect.y + 3.0), renderer)
             }
             PopupKind::Custom { width, height, bomb_count } => {
-                text("Width" .to_owned(), Align::Mid(body_rect.x + 17.0), Align::Beg(body_rect.y +  4.0), renderer);
-                text("Height".to_owned(), Align::Mid(body_rect.x + 17.0), Align::Beg(body_rect.y + 14.0), renderer);
-                text("Bombs" .to_owned(), Align::Mid(body_rect.x + 17.0), Align::Beg(body_rect.y + 24.0), renderer);
-                number_field(width,  format!("{:?} - {:?}", MIN_WIDTH,  MAX_WIDTH),  Align::End(body_rect.right()-3.0), Align::Beg(body_rect.y +  2.0), 41.0, state, renderer, &mut id_add);
-                number_field(height, format!("{:?} - {:?}", MIN_HEIGHT, MAX_HEIGHT), Align::End(body_rect.right()-3.0), Align::Beg(body_rect.y + 12.0), 41.0, state, renderer, &mut id_add);
+                let label_x = Align::Mid(body_rect.x + 3.0 + custom_label_width(renderer) / 2.0);
+                text(lang("popup.custom.width",  renderer), label_x, Align::Beg(body_rect.y +  4.0), renderer);
+                text(lang("popup.custom.height", renderer), label_x, Align::Beg(body_rect.y + 14.0), renderer);
+                text(lang("popup.custom.bombs",  renderer), label_x, Align::Beg(body_rect.y + 24.0), renderer);
+                number_field(width,  format!("{:?} - {:?}", MIN_WIDTH,  MAX_WIDTH),  Align::End(right), Align::Beg(body_rect.y +  2.0), NUMBER_FIELD_WIDTH, state, renderer, &mut id_add);
+                number_field(height, format!("{:?} - {:?}", MIN_HEIGHT, MAX_HEIGHT), Align::End(right), Align::Beg(body_rect.y + 12.0), NUMBER_FIELD_WIDTH, state, renderer, &mut id_add);
                 let (size, max_bombs) = match (width.parse::<usize>(), height.parse::<usize>()) {
                     (Ok(w), Ok(h)) if Difficulty::dimensions_in_range(w, h) => (Some((w, h)), Difficulty::max_bombs(w, h)),
                     _ => (None, None),
@@ -204,21 +694,178 @@ impl Popup {
                     Some(m) => format!("0 - {:?}", m),
                     _ => "".to_owned(),
                 };
-                number_field(bomb_count, bomb_hint, Align::End(body_rect.right()-3.0), Align::Beg(body_rect.y+22.0), 41.0, state, renderer, &mut id_add);
+                number_field(bomb_count, bomb_hint, Align::End(right), Align::Beg(body_rect.y+22.0), NUMBER_FIELD_WIDTH, state, renderer, &mut id_add);
 
                 let diff = match (size, bomb_count.parse::<usize>()) {
                     (Some((w, h)), Ok(b)) => Difficulty::custom(w, h, b),
                     _ => None
                 };
 
-                if button("Submit".to_owned(), Align::End(body_rect.right()-3.0), Align::End(body_rect.bottom()-3.0), diff.is_none(), state, renderer, &mut id_add).released() {
+                let submit = lang("button.submit", renderer);
+                let cancel_x = left_of(right, &submit, renderer);
+                if default_button(submit, Align::End(right), bottom, diff.is_none(), state, renderer, &mut id_add).released() {
                     if let Some(difficulty) = diff {
-                        return_value = Some(PopupReturn::NewGame { difficulty });
+                        return_value = Some(PopupReturn::NewGame { difficulty, daily: false });
                         close = true;
                     }
                 }
-                close = close | button("Cancel".to_owned(), Align::End(body_rect.right()-36.0), Align::End(body_rect.bottom()-3.0), false, state, renderer, &mut id_add).released();
+                close |= button(lang("button.cancel", renderer), Align::End(cancel_x), bottom, false, state, renderer, &mut id_add).released();
             }
+            PopupKind::Settings { tab, settings, skins, languages } => {
+                let before = settings.clone();
+                let options = settings_options(renderer.lang(), skins, languages);
+                let (_, widget_width) = settings_columns(&options, &settings.bindings, renderer);
+                let slider_width = widget_width - text_size(&Volume::percent(1.0), renderer).x - 3.0;
+
+                let tab_labels: Vec<String> = SettingsTab::ALL.iter().map(|t| lang(t.label_key(), renderer)).collect();
+                let mut tab_index = SettingsTab::ALL.iter().position(|t| t == tab).unwrap_or(0);
+                id_add += 1;
+                if elements::tabs(id.wrapping_add(id_add), &tab_labels, &mut tab_index, Align::Beg(body_rect.x + MARGIN), Align::Beg(body_rect.y + MARGIN), state, renderer) {
+                    *tab = SettingsTab::ALL[tab_index];
+                }
+
+                let row_y = |row: usize, offset: f32| Align::Beg(body_rect.y + SETTINGS_TOP + row as f32 * SETTINGS_ROW_HEIGHT + offset);
+                let label = |key: &str, row: usize, renderer: &mut Renderer| {
+                    text(lang(key, renderer), Align::Beg(body_rect.x + MARGIN), row_y(row, 2.0), renderer)
+                };
+                // Sliders have their value written just to the left of them
+                let value = |value: String, row: usize, renderer: &mut Renderer| {
+                    text(value, Align::End(right - slider_width - 3.0), row_y(row, 2.0), renderer)
+                };
+                let widget_x = Align::End(right);
+
+                match tab {
+                    SettingsTab::Gameplay => {
+                        label("popup.settings.mode", 0, renderer);
+                        let mut mode = GameMode::ALL.iter().position(|m| *m == settings.mode).unwrap_or(0);
+                        if select(&options[0], &mut mode, widget_x, row_y(0, 0.0), widget_width, state, renderer, &mut id_add) {
+                            settings.mode = GameMode::ALL[mode];
+                        }
+                        label("menu.auto_flag", 1, renderer);
+                        checkbox(&mut settings.assists.auto_flag, widget_x, row_y(1, 1.0), state, renderer, &mut id_add);
+                        label("menu.auto_open", 2, renderer);
+                        checkbox(&mut settings.assists.auto_open, widget_x, row_y(2, 1.0), state, renderer, &mut id_add);
+                    }
+                    SettingsTab::Display => {
+                        label("popup.settings.skin", 0, renderer);
+                        let mut skin = skins.iter().position(|(name, _)| *name == settings.skin).unwrap_or(0);
+                        if select(&options[1], &mut skin, widget_x, row_y(0, 0.0), widget_width, state, renderer, &mut id_add) {
+                            settings.skin = skins[skin].0.clone();
+                        }
+                        label("popup.settings.face", 1, renderer);
+                        let mut face = FaceType::ALL.iter().position(|f| *f == settings.face_type).unwrap_or(0);
+                        if select(&options[2], &mut face, widget_x, row_y(1, 0.0), widget_width, state, renderer, &mut id_add) {
+                            settings.face_type = FaceType::ALL[face];
+                        }
+                        label("popup.settings.language", 2, renderer);
+                        let mut language = languages.iter().position(|(code, _)| *code == settings.language).unwrap_or(0);
+                        if select(&options[3], &mut language, widget_x, row_y(2, 0.0), widget_width, state, renderer, &mut id_add) {
+                            settings.language = languages[language].0.clone();
+                        }
+                        label("menu.number_cues", 3, renderer);
+                        checkbox(&mut settings.number_cues, widget_x, row_y(3, 1.0), state, renderer, &mut id_add);
+                        label("menu.screen_shake", 4, renderer);
+                        checkbox(&mut settings.shake_enabled, widget_x, row_y(4, 1.0), state, renderer, &mut id_add);
+                        label("popup.settings.auto_scale", 5, renderer);
+                        checkbox(&mut settings.auto_scale, widget_x, row_y(5, 1.0), state, renderer, &mut id_add);
+                        // Picking a scale turns off auto scaling, like in the scale menu
+                        label("menu.scale", 6, renderer);
+                        value(format!("{}*", settings.scale), 6, renderer);
+                        if slider(&mut settings.scale, (1.0, 8.0, 1.0), widget_x, row_y(6, 1.0), slider_width, state, renderer, &mut id_add) {
+                            settings.auto_scale = false;
+                        }
+                    }
+                    SettingsTab::Sound => {
+                        label("menu.mute", 0, renderer);
+                        checkbox(&mut settings.volume.muted, widget_x, row_y(0, 1.0), state, renderer, &mut id_add);
+                        label("sound.master", 1, renderer);
+                        value(Volume::percent(settings.volume.master), 1, renderer);
+                        slider(&mut settings.volume.master, (0.0, 1.0, 0.05), widget_x, row_y(1, 1.0), slider_width, state, renderer, &mut id_add);
+                        for (i, category) in SoundCategory::ALL.into_iter().enumerate() {
+                            let mut level = settings.volume.category(category);
+                            label(category.label_key(), i + 2, renderer);
+                            value(Volume::percent(level), i + 2, renderer);
+                            if slider(&mut level, (0.0, 1.0, 0.05), widget_x, row_y(i + 2, 1.0), slider_width, state, renderer, &mut id_add) {
+                                settings.volume.set_category(category, level);
+                            }
+                        }
+                    }
+                    SettingsTab::Controls => {
+                        for (row, action) in Action::ALL.into_iter().enumerate() {
+                            label(action.label_key(), row, renderer);
+                            text(settings.bindings.label(action, renderer.lang()), widget_x, row_y(row, 2.0), renderer);
+                        }
+                        for (row, (action, keys)) in CONTROLS.iter().enumerate() {
+                            let row = row + Action::ALL.len();
+                            label(action, row, renderer);
+                            text(lang(keys, renderer), widget_x, row_y(row, 2.0), renderer);
+                        }
+                        if button(lang("popup.settings.change", renderer), Align::Beg(body_rect.x + MARGIN), bottom, false, state, renderer, &mut id_add).released() {
+                            return_value = Some(PopupReturn::EditControls(settings.clone()));
+                        }
+                    }
+                }
+
+                close |= default_button(lang("button.close", renderer), Align::End(right), bottom, false, state, renderer, &mut id_add).released();
+                if *settings != before {
+                    return_value = Some(PopupReturn::Settings(settings.clone()));
+                }
+            }
+            PopupKind::Controls { settings, listening, ready, held } => {
+                let before = settings.clone();
+                if let Some(action) = *listening {
+                    if !state.focus_scope || state.escape_pressed() {
+                        *listening = None;
+                    } else if !*ready {
+                        *ready = bindings::held_input(state).is_none();
+                    } else if let Some(input) = bindings::listen(held, state) {
+                        settings.bindings.bind(input, action);
+                        *listening = None;
+                    }
+                }
+
+                // Each action's name with its buttons, and what it's bound to underneath
+                let (add, clear) = (lang("popup.controls.add", renderer), lang("popup.controls.clear", renderer));
+                let clear_x = left_of(right, &add, renderer);
+                for (i, action) in Action::ALL.into_iter().enumerate() {
+                    let y = body_rect.y + MARGIN + i as f32 * CONTROLS_BLOCK_HEIGHT;
+                    text(lang(action.label_key(), renderer), Align::Beg(body_rect.x + MARGIN), Align::Beg(y + 2.0), renderer);
+                    let bound = match *listening == Some(action) {
+                        true  => lang("popup.controls.listening", renderer),
+                        false => settings.bindings.label(action, renderer.lang()),
+                    };
+                    text(bound, Align::Beg(body_rect.x + MARGIN + 4.0), Align::Beg(y + 12.0), renderer);
+                    if button(add.clone(), Align::End(right), Align::Beg(y), false, state, renderer, &mut id_add).released() {
+                        (*listening, *ready, *held) = (Some(action), false, None);
+                    }
+                    let nothing_bound = settings.bindings.inputs(action).next().is_none();
+                    if button(clear.clone(), Align::End(clear_x), Align::Beg(y), nothing_bound, state, renderer, &mut id_add).released() {
+                        settings.bindings.clear(action);
+                    }
+                }
+
+                let presets_y = body_rect.y + MARGIN + Action::ALL.len() as f32 * CONTROLS_BLOCK_HEIGHT;
+                text(lang("popup.controls.presets", renderer), Align::Beg(body_rect.x + MARGIN), Align::Beg(presets_y), renderer);
+                let mut x = body_rect.x + MARGIN;
+                for preset in Preset::ALL {
+                    let label = lang(preset.label_key(), renderer);
+                    let next_x = x + button_size(&label, renderer).x + BUTTON_GAP;
+                    if button(label, Align::Beg(x), Align::Beg(presets_y + CONTROLS_PRESETS_GAP), false, state, renderer, &mut id_add).released() {
+                        settings.bindings = Bindings::preset(preset);
+                        *listening = None;
+                    }
+                    x = next_x;
+                }
+
+                close |= default_button(lang("button.close", renderer), Align::End(right), bottom, false, state, renderer, &mut id_add).released();
+                if *settings != before {
+                    return_value = Some(PopupReturn::Settings(settings.clone()));
+                }
+            }
+        }
+
+        if state.focus_scope && state.escape_pressed() {
+            close = true;
         }
 
         // Dragging the popup around
@@ -231,15 +878,15 @@ impl Popup {
         }
 
         if state.active_item == id {
-            state.hot_item = super::state::SelectedItem::Unavailable;
-            self.pos = state.mouse_pos() - *drag_offset;
+            state.hot_item = SelectedItem::Unavailable;
+            self.anchor = (state.mouse_pos() - *drag_offset + self.size / 2.0) / state.screen_size();
         }
         
         // let title_text_size = renderer.text_renderer.text_size(&self.title, None).y;
         renderer.draw(DrawShape::text(
             title_rect.x + 2.0,
             title_rect.y + 2.0,
-            self.title.clone(), None, None, None, renderer.style().popup_title_text()
+            title, None, None, renderer.style().popup_title_text()
         ));
         renderer.draw(DrawShape::nineslice(title_rect, renderer.style().popup_title()));
         renderer.draw(DrawShape::nineslice(body_rect,  renderer.style().popup_body()));
@@ -261,6 +908,9 @@ impl Popup {
 
         renderer.draw(DrawShape::image(rect.x, rect.y, source, None));
 
+        if button_state == ButtonState::Released {
+            renderer.sound_player_mut().play(SoundEffect::Click);
+        }
         button_state == ButtonState::Released
     }
 }
\ No newline at end of file
@@ -0,0 +1,198 @@
+// Describes where everything is on the spritesheet, so it can be moved around without touching any code.
+// Sprites are [x, y, w, h], nineslices are [x, y, w, h, padding], grids are [x, y, w, h, columns] of equally sized cells,
+// and palette colours are "#rrggbb" or "#rrggbbaa".
+// Everything in a theme's section is looked up first, then the section of the theme it's based on (if it is), then the shared section.
+// Skins can also say which built-in skin they're based on, which is where anything they leave out comes from.
+// A section can also recolour the spritesheet, with colour swaps for the whole sheet ("*"), a sprite ("name") or a grid cell ("name:index").
+
+use std::collections::HashMap;
+
+use macroquad::{color::Color, math::{Rect, Vec2}};
+use nanoserde::DeJson;
+
+use super::style::Nineslice;
+
+#[derive(DeJson, Default)]
+#[nserde(default)]
+struct AtlasFile {
+    base:   String,
+    shared: SectionFile,
+    themes: HashMap<String, SectionFile>,
+}
+
+#[derive(DeJson, Default)]
+#[nserde(default)]
+struct SectionFile {
+    base:       String,
+    sprites:    HashMap<String, Vec<f32>>,
+    nineslices: HashMap<String, Vec<f32>>,
+    grids:      HashMap<String, Vec<f32>>,
+    palette:    HashMap<String, String>,
+    recolor:    HashMap<String, HashMap<String, String>>,
+}
+
+#[derive(Clone, Copy)]
+pub struct Grid {
+    cell:    Rect,
+    columns: u32,
+}
+
+impl Grid {
+    pub fn cell(&self, index: u32) -> Rect {
+        let columns = self.columns.max(1);
+        Rect::new(
+            self.cell.x + (index % columns) as f32 * self.cell.w,
+            self.cell.y + (index / columns) as f32 * self.cell.h,
+            self.cell.w,
+            self.cell.h,
+        )
+    }
+}
+
+pub type Recolor = HashMap<[u8; 4], [u8; 4]>;
+
+#[derive(Default, Clone)]
+pub struct Section {
+    base:       Option<String>,
+    sprites:    HashMap<String, Rect>,
+    nineslices: HashMap<String, Nineslice>,
+    grids:      HashMap<String, Grid>,
+    palette:    HashMap<String, Color>,
+    recolor:    HashMap<String, Recolor>,
+}
+
+#[derive(Default)]
+pub struct Atlas {
+    base:   Option<String>,
+    shared: Section,
+    themes: HashMap<String, Section>,
+}
+
+impl Atlas {
+    pub fn parse(data: &str) -> Result<Atlas, String> {
+        let file = AtlasFile::deserialize_json(data).map_err(|e| format!("{:?}", e))?;
+        Ok(Atlas {
+            base:   Some(file.base).filter(|b| !b.is_empty()),
+            shared: Section::from_file(file.shared),
+            themes: file.themes.into_iter().map(|(name, s)| (name, Section::from_file(s))).collect(),
+        })
+    }
+
+    pub fn base(&self) -> Option<&str> {
+        self.base.as_deref()
+    }
+
+    // Everything for a single theme in one place, the theme's own entries winning over the ones it's based on.
+    // Only the theme's own recolouring is kept, whatever it's based on has its own spritesheet.
+    pub fn section(&self, theme: Option<&str>) -> Section {
+        let mut section = Section::default();
+        let mut seen = Vec::new();
+        let mut next = theme;
+        // Stop if the bases go round in a circle
+        while let Some(name) = next.filter(|n| !seen.contains(n)) {
+            let Some(theme) = self.themes.get(name) else { break };
+            seen.push(name);
+            section.fill_from(theme, Vec2::ZERO);
+            next = theme.base.as_deref();
+        }
+        section.fill_from(&self.shared, Vec2::ZERO);
+        section.recolor = match theme.and_then(|t| self.themes.get(t)) {
+            Some(theme) => theme.recolor.clone(),
+            None        => self.shared.recolor.clone(),
+        };
+        section
+    }
+}
+
+impl Section {
+    fn from_file(file: SectionFile) -> Section {
+        // Anything that doesn't make sense is left out (and complained about)
+        let numbers = |kind: &str, name: &str, values: &[f32], len: usize| match values.len() == len {
+            true  => Some(values.to_vec()),
+            false => {
+                macroquad::logging::error!("Atlas {} '{}' should have {} numbers, not {}!", kind, name, len, values.len());
+                None
+            }
+        };
+        let sprites = file.sprites.into_iter()
+            .filter_map(|(name, v)| numbers("sprite", &name, &v, 4).map(|v| (name, Rect::new(v[0], v[1], v[2], v[3]))))
+            .collect();
+        let nineslices = file.nineslices.into_iter()
+            .filter_map(|(name, v)| numbers("nineslice", &name, &v, 5).map(|v| (name, Nineslice::new(v[0], v[1], v[2], v[3], v[4]))))
+            .collect();
+        let grids = file.grids.into_iter()
+            .filter_map(|(name, v)| numbers("grid", &name, &v, 5).map(|v| (name, Grid { cell: Rect::new(v[0], v[1], v[2], v[3]), columns: v[4] as u32 })))
+            .collect();
+        let palette = file.palette.into_iter()
+            .filter_map(|(name, hex)| match parse_color(&hex) {
+                Some(c) => Some((name, c)),
+                None => {
+                    macroquad::logging::error!("Atlas colour '{}' isn't a valid colour: '{}'!", name, hex);
+                    None
+                }
+            })
+            .collect();
+        let recolor = file.recolor.into_iter()
+            .map(|(name, swaps)| (name, swaps.into_iter()
+                .filter_map(|(from, to)| match (parse_rgba(&from), parse_rgba(&to)) {
+                    (Some(from), Some(to)) => Some((from, to)),
+                    _ => {
+                        macroquad::logging::error!("Atlas recolour '{}' -> '{}' isn't a valid pair of colours!", from, to);
+                        None
+                    }
+                })
+                .collect()
+            ))
+            .collect();
+        Section { base: Some(file.base).filter(|b| !b.is_empty()), sprites, nineslices, grids, palette, recolor }
+    }
+
+    pub fn sprite(&self, name: &str)    -> Option<Rect>      { self.sprites   .get(name).copied() }
+    pub fn nineslice(&self, name: &str) -> Option<Nineslice> { self.nineslices.get(name).copied() }
+    pub fn grid(&self, name: &str)      -> Option<Grid>      { self.grids     .get(name).copied() }
+    pub fn color(&self, name: &str)     -> Option<Color>     { self.palette   .get(name).copied() }
+    pub fn recolor(&self)               -> &HashMap<String, Recolor> { &self.recolor }
+
+    // Where a sprite, nineslice or grid cell ("name:index") is
+    pub fn region(&self, name: &str) -> Option<Rect> {
+        if let Some((grid, index)) = name.split_once(':') {
+            return self.grid(grid).zip(index.parse().ok()).map(|(g, i)| g.cell(i));
+        }
+        self.sprite(name).or_else(|| self.nineslice(name).map(|n| n.rect))
+    }
+
+    // Adds anything this is missing from another section, whose sprites are 'offset' away on the spritesheet
+    pub fn fill_from(&mut self, other: &Section, offset: Vec2) {
+        let moved = |r: &Rect| r.offset(offset);
+        for (name, r) in &other.sprites {
+            self.sprites.entry(name.clone()).or_insert_with(|| moved(r));
+        }
+        for (name, n) in &other.nineslices {
+            self.nineslices.entry(name.clone()).or_insert_with(|| Nineslice { rect: moved(&n.rect), padding: n.padding });
+        }
+        for (name, g) in &other.grids {
+            self.grids.entry(name.clone()).or_insert_with(|| Grid { cell: moved(&g.cell), columns: g.columns });
+        }
+        for (name, c) in &other.palette {
+            self.palette.entry(name.clone()).or_insert(*c);
+        }
+    }
+}
+
+// "#rrggbb" or "#rrggbbaa"
+pub fn parse_color(hex: &str) -> Option<Color> {
+    parse_rgba(hex).map(|[r, g, b, a]| Color::from_rgba(r, g, b, a))
+}
+
+fn parse_rgba(hex: &str) -> Option<[u8; 4]> {
+    let hex = hex.strip_prefix('#')?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| hex.get(i*2..i*2+2).and_then(|c| u8::from_str_radix(c, 16).ok());
+    let alpha = match hex.len() {
+        8 => channel(3)?,
+        _ => 255,
+    };
+    Some([channel(0)?, channel(1)?, channel(2)?, alpha])
+}
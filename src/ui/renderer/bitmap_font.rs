@@ -0,0 +1,105 @@
+// Reads fonts in the text version of the BMFont format, see https://www.angelcode.com/products/bmfont/doc/file_format.html
+// Each line is a tag followed by key=value pairs, and the only tags that matter to us are common, page, char and kerning.
+
+use std::collections::HashMap;
+
+use macroquad::math::{vec2, Rect, Vec2};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph {
+    // Where it is on its page
+    pub source:  Rect,
+    // Where it's drawn, relative to the top left of where it goes on the line
+    pub offset:  Vec2,
+    pub advance: f32,
+    pub page:    usize,
+}
+
+#[derive(Debug, Default)]
+pub struct BitmapFont {
+    line_height: f32,
+    // The file names of each page's texture
+    pages:    Vec<String>,
+    glyphs:   HashMap<char, Glyph>,
+    kernings: HashMap<(char, char), f32>,
+}
+
+impl BitmapFont {
+    pub fn parse(data: &str) -> Result<BitmapFont, String> {
+        let mut font = BitmapFont::default();
+
+        for (line_number, line) in data.lines().enumerate() {
+            let tokens = tokenize(line);
+            let Some((tag, rest)) = tokens.split_first() else { continue };
+            let values: HashMap<&str, &str> = rest.iter().filter_map(|t| t.split_once('=')).collect();
+            let error = |key: &str| format!("line {}: '{}' is missing or isn't a number", line_number + 1, key);
+            let number = |key: &str| values.get(key).and_then(|v| v.parse::<f32>().ok()).ok_or_else(|| error(key));
+
+            match tag.as_str() {
+                "common" => font.line_height = number("lineHeight")?,
+                "page" => {
+                    let id = number("id")? as usize;
+                    let file = values.get("file").ok_or_else(|| error("file"))?.to_string();
+                    if font.pages.len() <= id {
+                        font.pages.resize(id + 1, String::new());
+                    }
+                    font.pages[id] = file;
+                }
+                "char" => {
+                    // Characters that aren't valid unicode can't be typed anyway
+                    let Some(c) = char::from_u32(number("id")? as u32) else { continue };
+                    font.glyphs.insert(c, Glyph {
+                        source:  Rect::new(number("x")?, number("y")?, number("width")?, number("height")?),
+                        offset:  vec2(number("xoffset")?, number("yoffset")?),
+                        advance: number("xadvance")?,
+                        page:    number("page").unwrap_or(0.0) as usize,
+                    });
+                }
+                "kerning" => {
+                    let pair = (char::from_u32(number("first")? as u32), char::from_u32(number("second")? as u32));
+                    if let (Some(first), Some(second)) = pair {
+                        font.kernings.insert((first, second), number("amount")?);
+                    }
+                }
+                _ => (),
+            }
+        }
+        if font.glyphs.is_empty() {
+            return Err("no characters".to_owned());
+        }
+        Ok(font)
+    }
+
+    pub fn line_height(&self) -> f32    { self.line_height }
+    pub fn pages(&self)       -> &[String] { &self.pages }
+
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c)
+    }
+
+    pub fn kerning(&self, first: char, second: char) -> f32 {
+        self.kernings.get(&(first, second)).copied().unwrap_or(0.0)
+    }
+}
+
+// Splits on spaces, except for inside quotes, which are removed
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
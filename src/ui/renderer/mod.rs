@@ -1,25 +1,30 @@
-use macroquad::{camera::{set_camera, Camera2D}, color::{Color, WHITE}, math::{vec2, Rect, Vec2}, prelude::ImageFormat, rand::{gen_range, rand}, shapes::draw_rectangle, texture::{draw_texture, draw_texture_ex, DrawTextureParams, Texture2D}, window::{screen_height, screen_width}};
+use macroquad::{camera::{set_camera, Camera2D}, color::{Color, WHITE}, math::{vec2, Rect, Vec2}, rand::{gen_range, rand}, shapes::draw_rectangle, texture::{draw_texture_ex, DrawTextureParams, Texture2D}, window::{screen_height, screen_width}};
 
-use self::{sound::SoundPlayer, style::{Nineslice, Style, EXPLOSION_SOUND, SPRITESHEET, WIN_SOUND}, text_renderer::{Caret, TextRenderer}};
+use self::{skin::{BuiltIn, DEFAULT_SKIN}, sound::SoundPlayer, style::{Nineslice, Style}, text_renderer::{Caret, TextRenderer}};
+
+use crate::lang::{Lang, DEFAULT_LANGUAGE};
 
 use super::{menubar::Menubar, state::State, Round};
 
+pub mod atlas;
+pub mod bitmap_font;
+pub mod skin;
 pub mod style;
 pub mod sound;
 pub mod text_renderer;
 
 pub enum DrawShape {
-    Text { x: f32, y: f32, text: String, line_gap: Option<f32>, caret: Option<Caret>, click_pos: Option<Vec2>, color: Color },
+    Text { x: f32, y: f32, text: String, line_gap: Option<f32>, caret: Option<Caret>, color: Color },
     Rect { x: f32, y: f32, w: f32, h: f32, color: Color },
     Image { x: f32, y: f32, source: Rect, color: Color },
     ImageRect { dest: Rect, source: Rect, color: Color },
     Nineslice { dest: Rect, source: Rect, padding: f32 },
-    Texture { x: f32, y: f32, texture: Texture2D },
+    Texture { dest: Rect, source: Rect, texture: Texture2D },
 }
 
 impl DrawShape {
-    pub fn text(x: f32, y: f32, text: String, line_gap: Option<f32>, caret: Option<Caret>, click_pos: Option<Vec2>, color: Color) -> Self {
-        Self::Text { x, y, text, line_gap, caret, click_pos, color }
+    pub fn text(x: f32, y: f32, text: String, line_gap: Option<f32>, caret: Option<Caret>, color: Color) -> Self {
+        Self::Text { x, y, text, line_gap, caret, color }
     }
     pub fn rect(rect: Rect, color: Color) -> Self {
         Self::Rect { x: rect.x, y: rect.y, w: rect.w, h: rect.h, color }
@@ -33,8 +38,8 @@ impl DrawShape {
     pub fn nineslice(dest: Rect, source: Nineslice) -> Self {
         Self::Nineslice { dest, source: source.rect, padding: source.padding }
     }
-    pub fn texture(x: f32, y: f32, texture: Texture2D) -> Self {
-        Self::Texture { x, y, texture }
+    pub fn texture(dest: Rect, source: Rect, texture: Texture2D) -> Self {
+        Self::Texture { dest, source, texture }
     }
     pub fn round(&mut self) {
         match self {
@@ -43,19 +48,20 @@ impl DrawShape {
             Self::Image     { x, y, source, .. }           => { *x = x.round(); *y = y.round(); *source = source.round(); }
             Self::ImageRect { dest, source, .. }           => { *dest = dest.round(); *source = source.round(); }
             Self::Nineslice { dest, source, padding }      => { *dest = dest.round(); *source = source.round(); *padding = padding.round(); }
-            Self::Texture   { x, y, .. }                   => { *x = x.round(); *y = y.round(); }
+            Self::Texture   { dest, source, .. }           => { *dest = dest.round(); *source = source.round(); }
         }
     }
 }
 
 pub struct Renderer {
     style:        Style,
+    built_in:     BuiltIn,
+    lang:         Lang,
     sound_player: SoundPlayer,
 
     pub text_renderer: TextRenderer,
     pub draw_queue: Vec<DrawShape>,
     pub caret_timer: f32,
-    pub text_click_pos: Option<usize>,
 
     pub shake_enabled: bool,
     shake_timer: f32,
@@ -68,17 +74,18 @@ pub struct Renderer {
 
 impl Renderer {
     pub async fn new() -> Renderer {
-        let texture = Texture2D::from_file_with_format(SPRITESHEET, Some(ImageFormat::Png));
-        texture.set_filter(macroquad::texture::FilterMode::Nearest);
+        let built_in = BuiltIn::load();
+        let default_skin = skin::load(DEFAULT_SKIN, &built_in).expect("the default skin is built in");
         
         Renderer {
-            style: Style::new(texture),
-            sound_player: SoundPlayer::new(WIN_SOUND, EXPLOSION_SOUND).await,
+            style: Style::new(default_skin),
+            built_in,
+            lang: Lang::load(DEFAULT_LANGUAGE).expect("the default language is built in"),
+            sound_player: SoundPlayer::new().await,
 
             text_renderer: TextRenderer::new(),
             draw_queue: Vec::new(),
             caret_timer: 0.0,
-            text_click_pos: None,
 
             shake_enabled: true,
             shake_timer: 0.0,
@@ -92,6 +99,27 @@ impl Renderer {
     pub fn style(&self)         -> &Style       { &self.style }
     pub fn style_mut(&mut self) -> &mut Style   { &mut self.style }
     pub fn sound_player(&self)  -> &SoundPlayer { &self.sound_player }
+    pub fn sound_player_mut(&mut self) -> &mut SoundPlayer { &mut self.sound_player }
+    pub fn lang(&self)          -> &Lang        { &self.lang }
+
+    // Returns false if there's no language with that code, in which case nothing changes
+    pub fn set_language(&mut self, code: &str) -> bool {
+        match Lang::load(code) {
+            Some(lang) => { self.lang = lang; true }
+            None => false,
+        }
+    }
+
+    // Returns false if the skin couldn't be loaded, in which case nothing changes
+    pub async fn set_skin(&mut self, name: &str) -> bool {
+        let Some(mut skin) = skin::load(name, &self.built_in) else {
+            return false;
+        };
+        let (win, explosion) = (skin.win_sound.take(), skin.explosion_sound.take());
+        self.sound_player.set_sounds(win.as_deref(), explosion.as_deref()).await;
+        self.style.set_skin(skin);
+        true
+    }
 
     pub fn shake_stop(&mut self) {
         self.shake_damp *= 0.2;
@@ -153,19 +181,15 @@ impl Renderer {
         
         // Round all of the elements so they're nice and pixel perfect!
         self.draw_queue.iter_mut().for_each(|d| d.round());
-        self.text_click_pos = None;
         for draw_shape in self.draw_queue.iter().rev() {
-            let t = self.draw_shape(&draw_shape);
-            if self.text_click_pos == None {
-                self.text_click_pos = t;
-            }
+            self.draw_shape(draw_shape);
         }
     }
 
-    fn draw_shape(&self, draw_shape: &DrawShape) -> Option<usize> {
+    fn draw_shape(&self, draw_shape: &DrawShape) {
         match &draw_shape {
-            &DrawShape::Text { x, y, text, line_gap, caret, click_pos, color } =>
-                return self.text_renderer.draw_text(text, if self.caret_timer < 0.5 {*caret} else {None}, *click_pos, *x, *y, *color, *line_gap),
+            &DrawShape::Text { x, y, text, line_gap, caret, color } =>
+                self.text_renderer.draw_text(text, if self.caret_timer < 0.5 {*caret} else {None}, *x, *y, *color, *line_gap),
             &DrawShape::Rect { x, y, w, h, color } => draw_rectangle(*x, *y, *w, *h, *color),
             &DrawShape::Image { x, y, source, color } => {
                 let params = DrawTextureParams {
@@ -195,11 +219,15 @@ impl Renderer {
                     draw_texture_ex(&self.style().texture(), d.x, d.y, WHITE, params);
                 }
             },
-            &DrawShape::Texture { x, y, texture } => {
-                draw_texture(texture, *x, *y, WHITE);
+            &DrawShape::Texture { dest, source, texture } => {
+                let params = DrawTextureParams {
+                    dest_size: Some(dest.size()),
+                    source: Some(*source),
+                    ..Default::default()
+                };
+                draw_texture_ex(texture, dest.x, dest.y, WHITE, params);
             }
         }
-        None
     }
 }
 
@@ -0,0 +1,239 @@
+// Skins change how everything looks and sounds.
+// Light, Dark, High Contrast and Colour Blind are built in (the last two are recoloured versions of the first two), and on desktop any folder in the skins directory with a spritesheet.png in it is a skin too.
+// It can have an atlas.json (otherwise everything's where it is on the built-in spritesheet), and a win.ogg and explosion.ogg.
+// Anything a skin leaves out comes from the built-in skin it's based on, which is Light unless its atlas says otherwise.
+
+use macroquad::{color::BLANK, math::{vec2, Rect}, prelude::ImageFormat, texture::{FilterMode, Image, Texture2D}};
+
+use super::{atlas::{Atlas, Section}, style::{ATLAS, SPRITESHEET}};
+
+pub const DEFAULT_SKIN: &str = "light";
+pub const BUILT_IN_SKINS: [(&str, &str); 4] = [
+    ("light",         "Light"),
+    ("dark",          "Dark"),
+    ("high_contrast", "High Contrast"),
+    ("colour_blind",  "Colour Blind"),
+];
+
+pub struct Skin {
+    pub name:            String,
+    pub texture:         Texture2D,
+    pub section:         Section,
+    pub win_sound:       Option<Vec<u8>>,
+    pub explosion_sound: Option<Vec<u8>>,
+}
+
+// The built-in spritesheet and atlas, which every skin falls back to
+pub struct BuiltIn {
+    image:   Image,
+    texture: Texture2D,
+    atlas:   Atlas,
+}
+
+impl BuiltIn {
+    pub fn load() -> BuiltIn {
+        let image = Image::from_file_with_format(SPRITESHEET, Some(ImageFormat::Png)).unwrap_or_else(|e| {
+            macroquad::logging::error!("Error '{:?}' loading the spritesheet!", e);
+            Image::empty()
+        });
+        let texture = new_texture(&image);
+        let atlas = Atlas::parse(ATLAS).unwrap_or_else(|e| {
+            macroquad::logging::error!("Error '{}' loading the atlas!", e);
+            Atlas::default()
+        });
+        BuiltIn { image, texture, atlas }
+    }
+
+    // The spritesheet of a built-in skin, if it's been recoloured
+    fn recolored_image(&self, section: &Section) -> Option<Image> {
+        if section.recolor().is_empty() {
+            return None;
+        }
+        let mut image = self.image.clone();
+        recolor(&mut image, section);
+        Some(image)
+    }
+
+    fn skin(&self, name: &str) -> Skin {
+        let section = self.atlas.section(Some(name));
+        let texture = match self.recolored_image(&section) {
+            Some(image) => new_texture(&image),
+            None        => self.texture.clone(),
+        };
+        Skin {
+            name:            name.to_owned(),
+            texture,
+            section,
+            win_sound:       None,
+            explosion_sound: None,
+        }
+    }
+}
+
+// Every skin's name and what it's called in the menu, the built-in ones first
+pub fn list() -> Vec<(String, String)> {
+    let built_in = BUILT_IN_SKINS.iter().map(|(name, label)| (name.to_string(), label.to_string()));
+    let mut custom: Vec<(String, String)> = platform::skin_names().into_iter()
+        .filter(|name| !BUILT_IN_SKINS.iter().any(|(n, _)| n == name))
+        .map(|name| (name.clone(), name))
+        .collect();
+    custom.sort();
+    built_in.chain(custom).collect()
+}
+
+pub fn load(name: &str, built_in: &BuiltIn) -> Option<Skin> {
+    if BUILT_IN_SKINS.iter().any(|(n, _)| *n == name) {
+        return Some(built_in.skin(name));
+    }
+    let files = platform::skin_files(name)?;
+
+    let image = match Image::from_file_with_format(&files.spritesheet, Some(ImageFormat::Png)) {
+        Ok(i) => i,
+        Err(e) => {
+            macroquad::logging::error!("Error '{:?}' loading the spritesheet of skin '{}'!", e, name);
+            return None;
+        }
+    };
+    let atlas = match files.atlas.as_deref().map(Atlas::parse) {
+        Some(Ok(a)) => Some(a),
+        Some(Err(e)) => {
+            macroquad::logging::error!("Error '{}' loading the atlas of skin '{}'!", e, name);
+            return None;
+        }
+        None => None,
+    };
+
+    let base = atlas.as_ref().and_then(|a| a.base())
+        .filter(|b| BUILT_IN_SKINS.iter().any(|(n, _)| n == b))
+        .unwrap_or(DEFAULT_SKIN);
+    let base_section = built_in.atlas.section(Some(base));
+    let base_image = built_in.recolored_image(&base_section);
+
+    // The built-in spritesheet goes underneath the skin's, so anything missing can be taken from there instead
+    let Some(mut image) = stack_images(&image, base_image.as_ref().unwrap_or(&built_in.image)) else {
+        macroquad::logging::error!("The spritesheet of skin '{}' is too big!", name);
+        return None;
+    };
+    let mut section = match &atlas {
+        Some(a) => a.section(None),
+        None    => base_section.clone(),
+    };
+    section.fill_from(&base_section, vec2(0.0, (image.height - built_in.image.height) as f32));
+    if atlas.is_some() {
+        recolor(&mut image, &section);
+    }
+
+    Some(Skin {
+        name: name.to_owned(),
+        texture: new_texture(&image),
+        section,
+        win_sound:       files.win_sound,
+        explosion_sound: files.explosion_sound,
+    })
+}
+
+fn new_texture(image: &Image) -> Texture2D {
+    let texture = Texture2D::from_image(image);
+    texture.set_filter(FilterMode::Nearest);
+    texture
+}
+
+// Swaps colours everywhere first, then in each sprite, which can also use the swaps for everywhere
+fn recolor(image: &mut Image, section: &Section) {
+    let original = image.clone();
+    let everywhere = section.recolor().get("*");
+    let whole = Rect::new(0.0, 0.0, image.width as f32, image.height as f32);
+    let regions = std::iter::once((whole, None))
+        .chain(section.recolor().iter()
+            .filter(|(name, _)| *name != "*")
+            .filter_map(|(name, swaps)| match section.region(name) {
+                Some(r) => Some((r, Some(swaps))),
+                None => {
+                    macroquad::logging::error!("There's nothing called '{}' to recolour!", name);
+                    None
+                }
+            })
+        );
+    for (rect, swaps) in regions {
+        let Some(rect) = rect.intersect(whole) else { continue };
+        for y in rect.y as usize..rect.bottom() as usize {
+            for x in rect.x as usize..rect.right() as usize {
+                let i = (y * image.width as usize + x) * 4;
+                let from: [u8; 4] = original.bytes[i..i + 4].try_into().unwrap_or_default();
+                let to = swaps.and_then(|s| s.get(&from))
+                    .or_else(|| everywhere.and_then(|s| s.get(&from)))
+                    .unwrap_or(&from);
+                image.bytes[i..i + 4].copy_from_slice(to);
+            }
+        }
+    }
+}
+
+fn stack_images(top: &Image, bottom: &Image) -> Option<Image> {
+    let width = top.width.max(bottom.width);
+    let mut image = Image::gen_image_color(width, top.height.checked_add(bottom.height)?, BLANK);
+    for (y_offset, source) in [(0, top), (top.height, bottom)] {
+        let row_len = source.width as usize * 4;
+        for y in 0..source.height as usize {
+            let from = y * row_len;
+            let to   = (y + y_offset as usize) * width as usize * 4;
+            image.bytes[to..to + row_len].copy_from_slice(&source.bytes[from..from + row_len]);
+        }
+    }
+    Some(image)
+}
+
+struct SkinFiles {
+    spritesheet:     Vec<u8>,
+    atlas:           Option<String>,
+    win_sound:       Option<Vec<u8>>,
+    explosion_sound: Option<Vec<u8>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use std::path::PathBuf;
+
+    use super::SkinFiles;
+
+    fn skins_dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("minesweeper").join("skins"))
+    }
+
+    pub fn skin_names() -> Vec<String> {
+        let Some(entries) = skins_dir().and_then(|d| std::fs::read_dir(d).ok()) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().join("spritesheet.png").is_file())
+            .filter_map(|e| e.file_name().into_string().ok())
+            // The name gets saved in the settings, so keep it simple
+            .filter(|name| !name.contains(['"', '\\']))
+            .collect()
+    }
+
+    pub fn skin_files(name: &str) -> Option<SkinFiles> {
+        let dir = skins_dir()?.join(name);
+        Some(SkinFiles {
+            spritesheet:     std::fs::read(dir.join("spritesheet.png")).ok()?,
+            atlas:           std::fs::read_to_string(dir.join("atlas.json")).ok(),
+            win_sound:       std::fs::read(dir.join("win.ogg")).ok(),
+            explosion_sound: std::fs::read(dir.join("explosion.ogg")).ok(),
+        })
+    }
+}
+
+// There's nowhere to put skins on the web
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use super::SkinFiles;
+
+    pub fn skin_names() -> Vec<String> {
+        Vec::new()
+    }
+
+    pub fn skin_files(_name: &str) -> Option<SkinFiles> {
+        None
+    }
+}
@@ -1,15 +1,145 @@
-use macroquad::audio::{load_sound_from_bytes, play_sound, PlaySoundParams, Sound};
+use std::collections::HashMap;
+
+use macroquad::{audio::{load_sound_from_bytes, play_sound, PlaySoundParams, Sound}, time::get_time};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+    Win, Explosion, Dig, Open, Flag, Unflag, Chord, Click,
+}
+
+impl SoundEffect {
+    pub const ALL: [SoundEffect; 8] = [
+        SoundEffect::Win, SoundEffect::Explosion, SoundEffect::Dig, SoundEffect::Open,
+        SoundEffect::Flag, SoundEffect::Unflag, SoundEffect::Chord, SoundEffect::Click,
+    ];
+
+    fn built_in(&self) -> &'static [u8] {match self {
+        SoundEffect::Win       => include_bytes!("../../../resources/congrats.ogg"),
+        SoundEffect::Explosion => include_bytes!("../../../resources/explosion.ogg"),
+        SoundEffect::Dig       => include_bytes!("../../../resources/dig.wav"),
+        SoundEffect::Open      => include_bytes!("../../../resources/open.wav"),
+        SoundEffect::Flag      => include_bytes!("../../../resources/flag.wav"),
+        SoundEffect::Unflag    => include_bytes!("../../../resources/unflag.wav"),
+        SoundEffect::Chord     => include_bytes!("../../../resources/chord.wav"),
+        SoundEffect::Click     => include_bytes!("../../../resources/click.wav"),
+    }}
+    pub fn category(&self) -> SoundCategory {match self {
+        SoundEffect::Win   => SoundCategory::Jingles,
+        SoundEffect::Click => SoundCategory::Interface,
+        _                  => SoundCategory::Game,
+    }}
+    // How loud it is compared to the others
+    fn volume(&self) -> f32 {match self {
+        SoundEffect::Explosion => 0.3,
+        SoundEffect::Click     => 0.5,
+        _                      => 1.0,
+    }}
+    // The shortest time between it being played, so things like flagging a whole row at once don't get too loud
+    // (explosions are already spaced out by the exploder)
+    fn gap(&self) -> f64 {match self {
+        SoundEffect::Win | SoundEffect::Explosion => 0.0,
+        _ => 0.06,
+    }}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SoundCategory {
+    Game, Jingles, Interface,
+}
+
+impl SoundCategory {
+    pub const ALL: [SoundCategory; 3] = [SoundCategory::Game, SoundCategory::Jingles, SoundCategory::Interface];
+
+    pub fn name(&self) -> &'static str {match self {
+        SoundCategory::Game      => "game",
+        SoundCategory::Jingles   => "jingles",
+        SoundCategory::Interface => "interface",
+    }}
+    pub fn label_key(&self) -> &'static str {match self {
+        SoundCategory::Game      => "sound.game",
+        SoundCategory::Jingles   => "sound.jingles",
+        SoundCategory::Interface => "sound.interface",
+    }}
+}
+
+// Every volume is between 0 and 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Volume {
+    pub muted:  bool,
+    pub master: f32,
+    categories: [f32; SoundCategory::ALL.len()],
+}
+
+impl Default for Volume {
+    fn default() -> Self {
+        Volume { muted: false, master: 1.0, categories: [1.0; SoundCategory::ALL.len()] }
+    }
+}
+
+impl Volume {
+    pub fn category(&self, category: SoundCategory) -> f32 {
+        self.categories[category as usize]
+    }
+    pub fn set_category(&mut self, category: SoundCategory, volume: f32) {
+        self.categories[category as usize] = volume.clamp(0.0, 1.0);
+    }
+
+    // How volumes are shown to the player
+    pub fn percent(volume: f32) -> String {
+        format!("{}%", (volume * 100.0).round())
+    }
+
+    // Steps through 0%, 25%, 50%, 75% and 100%, going back to silent after full volume
+    pub fn next_step(volume: f32) -> f32 {
+        ((volume * 4.0).round() + 1.0) % 5.0 / 4.0
+    }
+
+    fn of(&self, effect: SoundEffect) -> f32 {
+        match self.muted {
+            true  => 0.0,
+            false => self.master * self.category(effect.category()) * effect.volume(),
+        }
+    }
+}
 
 pub struct SoundPlayer {
-    win:       Option<Sound>,
-    explosion: Option<Sound>,
+    sounds: HashMap<SoundEffect, Sound>,
+    // The built-in sounds, for skins that don't have their own
+    defaults: HashMap<SoundEffect, Sound>,
+    volume: Volume,
+    // When each sound was last played
+    last_played: HashMap<SoundEffect, f64>,
 }
 
 impl SoundPlayer {
-    pub async fn new(win: &[u8], explosion: &[u8]) -> SoundPlayer {
+    pub async fn new() -> SoundPlayer {
+        let mut defaults = HashMap::new();
+        for effect in SoundEffect::ALL {
+            if let Some(sound) = SoundPlayer::load(effect.built_in()).await {
+                defaults.insert(effect, sound);
+            }
+        }
         SoundPlayer {
-            win:       SoundPlayer::load(win)      .await,
-            explosion: SoundPlayer::load(explosion).await,
+            sounds: defaults.clone(),
+            defaults,
+            volume: Volume::default(),
+            last_played: HashMap::new(),
+        }
+    }
+
+    pub fn volume(&self) -> Volume { self.volume }
+    pub fn set_volume(&mut self, volume: Volume) {
+        self.volume = volume;
+    }
+
+    // Switches to a skin's sounds, or back to the built-in ones for any it doesn't have
+    pub async fn set_sounds(&mut self, win: Option<&[u8]>, explosion: Option<&[u8]>) {
+        self.sounds = self.defaults.clone();
+        for (effect, data) in [(SoundEffect::Win, win), (SoundEffect::Explosion, explosion)] {
+            let Some(data) = data else { continue };
+            if let Some(sound) = SoundPlayer::load(data).await {
+                self.sounds.insert(effect, sound);
+            }
         }
     }
 
@@ -21,22 +151,16 @@ impl SoundPlayer {
         sound.ok()
     }
 
-    fn play(sound: &Option<Sound>, params: PlaySoundParams) {
-        if let Some(sound) = sound {
-            play_sound(sound, params);
+    pub fn play(&mut self, effect: SoundEffect) {
+        let volume = self.volume.of(effect);
+        let now = get_time();
+        let too_soon = self.last_played.get(&effect).is_some_and(|t| now - t < effect.gap());
+        if volume <= 0.0 || too_soon {
+            return;
+        }
+        if let Some(sound) = self.sounds.get(&effect) {
+            play_sound(sound, PlaySoundParams { looped: false, volume });
+            self.last_played.insert(effect, now);
         }
     }
-
-    pub fn play_win(&self) {
-        SoundPlayer::play(
-            &self.win,
-            PlaySoundParams { looped: false, volume: 1.0 },
-        );
-    }
-    pub fn play_explosion(&self) {
-        SoundPlayer::play(
-            &self.explosion,
-            PlaySoundParams { looped: false, volume: 0.3 },
-        );
-    }
-}
\ No newline at end of file
+}
@@ -1,14 +1,10 @@
-use std::collections::HashMap;
-
 use macroquad::{color::Color, color_u8, math::{vec2, Rect, Vec2}, texture::Texture2D};
 
 use crate::ui::state::ButtonState;
 
-pub const fn rect(x: f32, y: f32, w: f32, h: f32) -> Rect {
-    Rect { x, y, w, h }
-}
+use super::{atlas::Section, skin::Skin};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub struct Nineslice {
     pub rect: Rect,
     pub padding: f32
@@ -21,10 +17,10 @@ impl Nineslice {
 }
 
 pub const SPRITESHEET:     &[u8] = include_bytes!("../../../resources/spritesheet.png");
-pub const WIN_SOUND:       &[u8] = include_bytes!("../../../resources/congrats.ogg");
-pub const EXPLOSION_SOUND: &[u8] = include_bytes!("../../../resources/explosion.ogg");
+pub const ATLAS:           &str  = include_str!("../../../resources/atlas.json");
 
 pub const SHADOW: Color = color_u8!(0, 0, 0, 128);
+pub const TIMER_WARNING: Color = color_u8!(255, 80, 80, 255);
 
 pub const STATUS_V_PAD: f32 = 3.0;
 
@@ -49,159 +45,158 @@ pub enum CounterDigit {
     Empty,
     Dash,
 }
-pub fn bomb_counter_digit(digit: CounterDigit) -> Rect {
-    let along = match digit {
-        CounterDigit::Digit(d) => d.min(9),
-        CounterDigit::Empty => 10,
-        CounterDigit::Dash  => 11,
-    };
-    Rect::new(
-        BOMB_COUNTER_DIGIT_SIZE.x * along as f32,
-        36.0,
-        BOMB_COUNTER_DIGIT_SIZE.x,
-        BOMB_COUNTER_DIGIT_SIZE.y,
-    )
-}
 
 pub const TIMER_SIZE:            Vec2     = vec2(21.0, 9.0);
 pub const TIMER_DIGIT_Y:         f32      = 2.0;
 pub const TIMER_DIGIT_POSITIONS: [f32; 4] = [2.0, 6.0, 12.0, 16.0];
 pub const TIMER_COLON_POSITION:  f32      = 10.0;
 
-pub fn timer_digit(digit: Option<u32>) -> Rect {
-    let d = digit.unwrap_or(10).min(10) as f32;
-    rect(2.0 + d * 3.0, 50.0, 3.0, 5.0)
-}
-pub const fn timer_colon(lit: bool) -> Rect {
-    let x = if lit { 1.0 } else { 0.0 };
-    rect(x, 50.0, 1.0, 5.0)
-}
-
 pub const MINEFIELD_TILE_SIZE: u32  = 9;
+// How far down a tile the underline below a number goes
+pub const NUMBER_CUE_Y:        f32  = 7.0;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FaceType { Fox, Nerd }
+impl FaceType {
+    pub const ALL: [FaceType; 2] = [FaceType::Fox, FaceType::Nerd];
 
-#[derive(PartialEq, Eq, Hash)]
-pub enum Theme { Light, Dark }
-impl Theme {
-    pub fn y(&self) -> f32 {match self {
-        Theme::Light =>  0.0,
-        Theme::Dark  => 18.0,
+    pub fn name(&self) -> &'static str {match self {
+        FaceType::Fox  => "fox",
+        FaceType::Nerd => "nerd",
+    }}
+    pub fn from_name(name: &str) -> Option<FaceType> {match name {
+        "fox"  => Some(FaceType::Fox),
+        "nerd" => Some(FaceType::Nerd),
+        _ => None,
     }}
 }
-#[derive(PartialEq, Eq)]
-pub enum FaceType { Fox, Nerd }
 pub enum Face { Idle, Scared, Win, Lose }
 
 pub struct Style {
     texture:   Texture2D,
-    colors:    HashMap<Theme, Colors>,
-    theme:     Theme,
+    section:   Section,
+    skin:      String,
+    // Goes up every time the skin changes, so anything drawn with the old one knows to redraw
+    revision:  u32,
     face_type: FaceType,
-}
-
-pub struct Colors {
-    pub text:             Color,
-    pub text_disabled:    Color,
-    pub text_url:         Color,
-    pub text_popup_title: Color,
-    pub menubar_idle:     (Color, Color, Color),
-    pub menubar_active:   (Color, Color, Color),
+    // Underlines numbers too, so they can be told apart by more than their colour
+    number_cues: bool,
 }
 
 impl Style {
-    pub fn new(texture: Texture2D) -> Style {
-        let mut colors = HashMap::new();
-
-        let data = texture.get_texture_data();
-        let pixel = |x: u32, y: u32| {
-            data.get_pixel(x, y)
-        };
-        for theme in [Theme::Light, Theme::Dark] {
-            let y = theme.y() as u32;
-            colors.insert(theme, Colors {
-                text:             pixel(86, 11+y),
-                text_disabled:    pixel(87, 11+y),
-                text_url:         pixel(88, 11+y),
-                text_popup_title: pixel(89, 11+y),
-                menubar_idle:     (pixel(86, 12+y), pixel(87, 12+y), pixel(88, 12+y)),
-                menubar_active:   (pixel(86, 13+y), pixel(87, 13+y), pixel(88, 13+y)),
-            });
-        }
-
+    pub fn new(skin: Skin) -> Style {
         Style {
-            texture,
-            colors,
-            theme:     Theme::Light,
+            texture:   skin.texture,
+            section:   skin.section,
+            skin:      skin.name,
+            revision:  0,
             face_type: FaceType::Fox,
+            number_cues: false,
         }
     }
     
     pub fn texture(&self) -> Texture2D { self.texture.clone() }
 
-    pub fn theme(&self)         -> &Theme { &self.theme }
-    pub fn set_theme(&mut self, t: Theme) { self.theme = t }
+    pub fn skin(&self)     -> &str { &self.skin }
+    pub fn revision(&self) -> u32  { self.revision }
+    pub fn set_skin(&mut self, skin: Skin) {
+        self.texture = skin.texture;
+        self.section = skin.section;
+        self.skin    = skin.name;
+        self.revision = self.revision.wrapping_add(1);
+    }
     pub fn face_type(&self)        -> &FaceType  { &self.face_type }
     pub fn set_face_type(&mut self, f: FaceType) { self.face_type = f }
+    pub fn number_cues(&self) -> bool { self.number_cues }
+    pub fn set_number_cues(&mut self, number_cues: bool) {
+        if self.number_cues != number_cues {
+            self.number_cues = number_cues;
+            self.revision = self.revision.wrapping_add(1);
+        }
+    }
+
+    // Anything missing from the atlas just doesn't show up
+    fn sprite(&self, name: &str) -> Rect {
+        self.section.sprite(name).unwrap_or_default()
+    }
+    fn nineslice(&self, name: &str) -> Nineslice {
+        self.section.nineslice(name).unwrap_or_default()
+    }
+    fn grid(&self, name: &str, index: u32) -> Rect {
+        self.section.grid(name).map(|g| g.cell(index)).unwrap_or_default()
+    }
+    fn color(&self, name: &str) -> Color {
+        self.section.color(name).unwrap_or_default()
+    }
 
-    pub fn background(&self) -> Nineslice { Nineslice::new(92.0, self.theme.y(), 3.0, 3.0, 1.0) }
+    pub fn background(&self) -> Nineslice { self.nineslice("background") }
 
-    pub fn text(&self)          -> Color { self.colors.get(&self.theme).map(|c| c.text)         .unwrap_or_default() }
-    pub fn text_disabled(&self) -> Color { self.colors.get(&self.theme).map(|c| c.text_disabled).unwrap_or_default() }
-    pub fn text_url(&self)      -> Color { self.colors.get(&self.theme).map(|c| c.text_url)     .unwrap_or_default() }
+    pub fn text(&self)          -> Color { self.color("text") }
+    pub fn text_disabled(&self) -> Color { self.color("text_disabled") }
+    pub fn text_url(&self)      -> Color { self.color("text_url") }
 
     pub fn menubar(&self, active: bool) -> (Color, Color, Color) {
-        self.colors.get(&self.theme)
-            .map(|c| if active {c.menubar_active} else {c.menubar_idle})
-            .unwrap_or_default()
+        match active {
+            true  => (self.color("menubar_active_background"), self.color("menubar_active_text"), self.color("menubar_active_other_text")),
+            false => (self.color("menubar_idle_background"),   self.color("menubar_idle_text"),   self.color("menubar_idle_other_text")),
+        }
     }
 
-    pub fn dropdown_background(&self) -> Nineslice { Nineslice::new(92.0, self.theme.y(), 3.0, 3.0, 1.0) }
-    pub fn dropdown_separator(&self)  -> Rect      { rect(94.0, 12.0 + self.theme.y(), 1.0, 2.0) }
+    pub fn dropdown_background(&self) -> Nineslice { self.nineslice("dropdown_background") }
+    pub fn dropdown_separator(&self)  -> Rect      { self.sprite("dropdown_separator") }
 
     pub fn button(&self, button_state: &ButtonState) -> (Vec2, Nineslice, Color) {
-        let (offset, y, text_col) = match button_state {
-            ButtonState::Disabled => (0.0, 6.0, self.text_disabled()),
+        let (offset, name, text_col) = match button_state {
+            ButtonState::Disabled => (0.0, "button_disabled", self.text_disabled()),
             ButtonState::Idle     |
-            ButtonState::Hovered  => (0.0, 0.0, self.text()),
-            _                     => (1.0, 3.0, self.text()),
+            ButtonState::Hovered  => (0.0, "button_idle",     self.text()),
+            _                     => (1.0, "button_pressed",  self.text()),
         };
-        (Vec2::splat(offset), Nineslice::new(92.0, y + self.theme.y(), 3.0, 3.0, 1.0), text_col)
+        (Vec2::splat(offset), self.nineslice(name), text_col)
     }
 
-    pub fn text_input(&self) -> Nineslice { Nineslice::new(92.0, 15.0 + self.theme.y(), 3.0, 3.0, 1.0) }
+    pub fn text_input(&self) -> Nineslice { self.nineslice("text_input") }
+    // Skins without a focus_ring colour use the text colour
+    pub fn focus_ring(&self) -> Color     { self.section.color("focus_ring").unwrap_or_else(|| self.text()) }
 
-    pub fn popup_title_text(&self) -> Color { self.colors.get(&self.theme).map(|c| c.text_popup_title).unwrap_or_default() }
-    pub fn popup_title(&self) -> Nineslice { Nineslice::new(86.0, 15.0 + self.theme.y(), 3.0, 3.0, 1.0) }
-    pub fn popup_body(&self)  -> Nineslice { Nineslice::new(89.0, 15.0 + self.theme.y(), 3.0, 3.0, 1.0) }
+    pub fn popup_title_text(&self) -> Color { self.color("text_popup_title") }
+    pub fn popup_title(&self) -> Nineslice { self.nineslice("popup_title") }
+    pub fn popup_body(&self)  -> Nineslice { self.nineslice("popup_body") }
     pub fn popup_close(&self, hovered: bool) -> Rect {
-        let y = if hovered { 7.0 } else { 0.0 };
-        rect(95.0, y + self.theme.y(), 7.0, 7.0)
+        match hovered {
+            true  => self.sprite("popup_close_hovered"),
+            false => self.sprite("popup_close"),
+        }
     }
 
-    pub fn bomb_counter_background(&self) -> Nineslice { Nineslice::new(92.0, 9.0 + self.theme.y(), 3.0, 3.0, 1.0) }
-    pub fn timer_background(&self)        -> Nineslice { Nineslice::new(92.0, 9.0 + self.theme.y(), 3.0, 3.0, 1.0) }
+    pub fn bomb_counter_background(&self) -> Nineslice { self.nineslice("bomb_counter_background") }
+    pub fn timer_background(&self)        -> Nineslice { self.nineslice("timer_background") }
 
-    pub fn minefield_tile(&self, id: u32) -> Rect {
-        Rect {
-            x: ((id % 9) * MINEFIELD_TILE_SIZE) as f32,
-            y: ((id / 9) * MINEFIELD_TILE_SIZE) as f32 + self.theme.y(),
-            w: MINEFIELD_TILE_SIZE as f32,
-            h: MINEFIELD_TILE_SIZE as f32,
-        }
+    pub fn bomb_counter_digit(&self, digit: CounterDigit) -> Rect {
+        let index = match digit {
+            CounterDigit::Digit(d) => d.min(9),
+            CounterDigit::Empty => 10,
+            CounterDigit::Dash  => 11,
+        };
+        self.grid("counter_digit", index)
     }
-    pub fn minefield_border(&self)   -> Nineslice { Nineslice::new(81.0, 11.0 + self.theme.y(), 5.0, 5.0, 2.0) }
-    pub fn minefield_selector(&self) -> Rect { rect(81.0, self.theme.y(), 11.0, 11.0) }
 
-    fn face_y(&self) -> f32 {
-        55.0 + match self.face_type {
-            FaceType::Fox  =>  0.0,
-            FaceType::Nerd => 17.0,
-        }
+    pub fn timer_digit(&self, digit: Option<u32>) -> Rect {
+        self.grid("timer_digit", digit.unwrap_or(10).min(10))
+    }
+    pub fn timer_colon(&self, lit: bool) -> Rect {
+        self.grid("timer_colon", lit as u32)
     }
 
+    pub fn minefield_tile(&self, id: u32) -> Rect { self.grid("minefield_tile", id) }
+    pub fn number_color(&self, number: u8) -> Color { self.color(&format!("number_{}", number)) }
+    pub fn minefield_border(&self)   -> Nineslice { self.nineslice("minefield_border") }
+    pub fn minefield_selector(&self) -> Rect      { self.sprite("minefield_selector") }
+
     pub fn face(&self, face: Face, blinking: bool, angry: bool) -> (Rect, Option<Rect>) {
-        let size = FACE_SIZE;
-        let face_rect = |index: usize| {
-            Rect::new(index as f32 * size.x, self.face_y(), size.x, size.y)
+        let grid = match self.face_type {
+            FaceType::Fox  => "face_fox",
+            FaceType::Nerd => "face_nerd",
         };
         
         let eye_index = match (&face, blinking, angry) {
@@ -211,8 +206,8 @@ impl Style {
             (_, false, true)  => Some(5), // Angry
             (_, true,  true)  => Some(6), // Both
         };
-        let face_index = face as usize;
+        let face_index = face as u32;
 
-        (face_rect(face_index), eye_index.map(|e| face_rect(e)))
+        (self.grid(grid, face_index), eye_index.map(|e| self.grid(grid, e)))
     }
-}
\ No newline at end of file
+}
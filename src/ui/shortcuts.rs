@@ -0,0 +1,56 @@
+// Keyboard shortcuts that work from anywhere, and how they're written next to their items in the menus
+
+use macroquad::input::{is_key_pressed, KeyCode};
+
+use super::{elements::is_ctrl_down, state::State};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Shortcut {
+    NewGame, Easy, Normal, Hard, Undo, Help,
+}
+
+impl Shortcut {
+    pub const ALL: [Shortcut; 6] = [Shortcut::NewGame, Shortcut::Easy, Shortcut::Normal, Shortcut::Hard, Shortcut::Undo, Shortcut::Help];
+
+    // The keys that do it, and whether ctrl needs holding for each of them. The first one's what the menus show
+    fn keys(&self) -> &'static [(bool, KeyCode)] {match self {
+        Shortcut::NewGame => &[(false, KeyCode::F2), (true, KeyCode::N)],
+        Shortcut::Easy    => &[(false, KeyCode::Key1)],
+        Shortcut::Normal  => &[(false, KeyCode::Key2)],
+        Shortcut::Hard    => &[(false, KeyCode::Key3)],
+        Shortcut::Undo    => &[(true,  KeyCode::Z)],
+        Shortcut::Help    => &[(false, KeyCode::F1)],
+    }}
+
+    // Ctrl's always written in English, like it is on most keyboards
+    pub fn label(&self) -> String {
+        let (ctrl, key) = self.keys()[0];
+        let key = match key {
+            KeyCode::F1   => "F1",
+            KeyCode::F2   => "F2",
+            KeyCode::Key1 => "1",
+            KeyCode::Key2 => "2",
+            KeyCode::Key3 => "3",
+            KeyCode::N    => "N",
+            KeyCode::Z    => "Z",
+            _ => "",
+        };
+        match ctrl {
+            true  => format!("Ctrl+{}", key),
+            false => key.to_owned(),
+        }
+    }
+
+    fn pressed(&self) -> bool {
+        let ctrl = is_ctrl_down();
+        self.keys().iter().any(|(needs_ctrl, key)| *needs_ctrl == ctrl && is_key_pressed(*key))
+    }
+}
+
+// The shortcut pressed this frame, if there was one. They're all off while typing into a text field
+pub fn pressed(state: &State) -> Option<Shortcut> {
+    if state.text_field.is_some() {
+        return None;
+    }
+    Shortcut::ALL.into_iter().find(|s| s.pressed())
+}
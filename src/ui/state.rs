@@ -1,11 +1,16 @@
 use std::collections::HashMap;
 
-use macroquad::{input::{get_last_key_pressed, is_key_down, is_mouse_button_down, is_mouse_button_pressed, is_mouse_button_released, mouse_position, KeyCode, MouseButton}, math::{vec2, Rect, Vec2}, time::get_frame_time, window::{screen_height, screen_width}};
+use macroquad::{input::{get_last_key_pressed, is_key_down, is_key_pressed, is_mouse_button_down, is_mouse_button_pressed, is_mouse_button_released, mouse_position, simulate_mouse_with_touch, touches, KeyCode, MouseButton, TouchPhase}, math::{vec2, Rect, Vec2}, time::get_frame_time, window::{screen_height, screen_width}};
 
 use super::{menubar::Menubar, minesweeper_element::MinesweeperElement, renderer::Renderer};
 
 pub type Id = u64;
 
+// How long a touch has to be held to count as a long press (a right click)
+const LONG_PRESS_TIME: f32 = 0.4;
+// How far a touch can move and still count as a tap, in pixels
+const TOUCH_SLOP: f32 = 8.0;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum SelectedItem {
     None,
@@ -53,9 +58,20 @@ impl ButtonState {
     }
 }
 
+// A finger on the screen that hasn't been turned into a click yet
+struct PendingTouch {
+    start: Vec2,
+    timer: f32,
+}
+
 pub struct State {
     mouse_pos: Vec2,
     mouse_buttons: HashMap<MouseButton, (bool, bool, bool)>, // (Down, Pressed, Released)
+    // Touches pretend to be the mouse, a tap is a left click and a long press is a right click
+    raw_mouse_pos: Vec2,
+    touch: Option<PendingTouch>,
+    touch_release: Option<MouseButton>,
+    using_touch: bool,
     screen_size: Vec2,
     auto_scale: bool,
     scale: f32,
@@ -64,8 +80,37 @@ pub struct State {
     pub hot_item:    SelectedItem,
     pub active_item: SelectedItem,
     pub text_field: Option<Id>,
+    // Whether the focused text field was drawn this frame, if it wasn't it's gone and shouldn't keep the focus
+    pub text_field_drawn: bool,
+    // The select whose list of options is open, which closes the same way if it isn't drawn
+    pub select: Option<Id>,
+    pub select_drawn: bool,
+    // Whether tab was pressed this frame, which moves the focus on to the next widget of the front popup
     pub tabbed: bool,
-    pub caret: usize,
+    // The widget that the keyboard's controlling, and whether it should have a ring around it.
+    // Clicking a widget focuses it too, but only tabbing to it shows the ring
+    pub focus: Option<Id>,
+    pub focus_visible: bool,
+    focus_drawn: bool,
+    focus_clicked: bool,
+    focus_uses_enter: bool,
+    // Only the widgets drawn while this is true can be tabbed to, which is while the front popup's being drawn
+    pub focus_scope: bool,
+    focus_order: Vec<Id>,
+    // Enter (or space) pressing the focused widget, or enter pressing the front popup's default button if the focused widget doesn't want it
+    focus_pressed: bool,
+    default_pressed: bool,
+    escape_pressed: bool,
+    // Whether any of the keys were used up by the ui, so the minefield shouldn't do anything with them
+    keys_used: bool,
+    // The caret and the other end of the selection in the focused text field, the same as each other when nothing's selected.
+    // Both count chars, not bytes
+    pub caret:     usize,
+    pub selection: usize,
+    // How many chars of the focused text field are scrolled off its left side
+    pub text_scroll: usize,
+    // The value the slider being dragged had when it was clicked, so letting go of it can tell if it moved
+    pub slider_start: f32,
 
     key_held: Option<KeyCode>,
     key_hold_timer: f32,
@@ -73,6 +118,8 @@ pub struct State {
 
 impl State {
     pub fn new() -> State {
+        // Touches are handled in update_touches, macroquad pretending they're the mouse would get in the way
+        simulate_mouse_with_touch(false);
         State {
             mouse_pos: Vec2::default(),
             mouse_buttons: HashMap::from([
@@ -80,6 +127,10 @@ impl State {
                 (MouseButton::Right,  (false, false, false)),
                 (MouseButton::Middle, (false, false, false)),
             ]),
+            raw_mouse_pos: Vec2::default(),
+            touch: None,
+            touch_release: None,
+            using_touch: false,
             screen_size: Vec2::default(),
             auto_scale:    true,
             scale: 2.0,
@@ -88,8 +139,25 @@ impl State {
             hot_item:     SelectedItem::None,
             active_item:  SelectedItem::None,
             text_field: None,
+            text_field_drawn: false,
+            select: None,
+            select_drawn: false,
             tabbed: false,
+            focus: None,
+            focus_visible: false,
+            focus_drawn: false,
+            focus_clicked: false,
+            focus_uses_enter: false,
+            focus_scope: false,
+            focus_order: Vec::new(),
+            focus_pressed: false,
+            default_pressed: false,
+            escape_pressed: false,
+            keys_used: false,
             caret: 0,
+            selection: 0,
+            text_scroll: 0,
+            slider_start: 0.0,
 
             key_held: None,
             key_hold_timer: 0.0,
@@ -99,6 +167,10 @@ impl State {
     pub fn mouse_pos(&self) -> Vec2 {
         self.mouse_pos
     }
+    // Whether the last input was a touch rather than the mouse
+    pub fn using_touch(&self) -> bool {
+        self.using_touch
+    }
     pub fn screen_size(&self) -> Vec2 {
         self.screen_size
     }
@@ -162,13 +234,22 @@ impl State {
     }
 
     pub fn begin(&mut self, menubar: &Menubar, minesweeper_element: &MinesweeperElement, renderer: &Renderer) {
-        self.mouse_pos = vec2(mouse_position().0, mouse_position().1) / self.scale;
+        // The mouse only takes over from touches again once it's actually moved
+        let raw_mouse_pos = vec2(mouse_position().0, mouse_position().1);
+        if raw_mouse_pos != self.raw_mouse_pos {
+            self.using_touch = false;
+        }
+        self.raw_mouse_pos = raw_mouse_pos;
+        if !self.using_touch {
+            self.mouse_pos = raw_mouse_pos / self.scale;
+        }
 
         for (&button, (down, pressed, released)) in &mut self.mouse_buttons.iter_mut() {
             *down     = is_mouse_button_down(button);
             *pressed  = is_mouse_button_pressed(button);
             *released = is_mouse_button_released(button);
         }
+        self.update_touches();
 
         let window_size = vec2(screen_width(), screen_height());
         if self.auto_scale {
@@ -181,7 +262,14 @@ impl State {
 
         self.screen_size = window_size / self.scale;
         self.hot_item = SelectedItem::None;
-        self.tabbed = false;
+
+        self.tabbed = is_key_pressed(KeyCode::Tab);
+        let enter = is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter);
+        self.focus_pressed   = self.focus.is_some() && self.focus_uses_enter && (enter || is_key_pressed(KeyCode::Space));
+        self.default_pressed = enter && !self.focus_pressed;
+        self.escape_pressed  = is_key_pressed(KeyCode::Escape);
+        self.focus_order.clear();
+        self.keys_used = false;
 
         if let Some(k) = get_last_key_pressed() {
             self.key_held = Some(k);
@@ -193,6 +281,95 @@ impl State {
         self.key_hold_timer += get_frame_time();
     }
 
+    // Turns touches into mouse clicks, so everything else can just deal with the mouse.
+    // Whether it's a tap or a long press isn't known until the finger's lifted or held long enough, 
+    // so the button is pressed then and let go of on the next frame
+    fn update_touches(&mut self) {
+        if let Some(button) = self.touch_release.take() {
+            self.mouse_buttons.insert(button, (false, false, true));
+        }
+
+        // Only one finger at a time
+        if let Some(touch) = touches().first() {
+            self.using_touch = true;
+            self.mouse_pos = touch.position / self.scale;
+
+            match touch.phase {
+                TouchPhase::Started => self.touch = Some(PendingTouch { start: touch.position, timer: 0.0 }),
+                TouchPhase::Ended if self.touch.is_some() => {
+                    self.touch = None;
+                    self.touch_press(MouseButton::Left);
+                }
+                TouchPhase::Cancelled => self.touch = None,
+                // Dragging the finger around isn't a tap
+                _ if self.touch.as_ref().is_some_and(|t| t.start.distance(touch.position) > TOUCH_SLOP) => self.touch = None,
+                _ => (),
+            }
+        }
+
+        if let Some(touch) = &mut self.touch {
+            touch.timer += get_frame_time();
+            if touch.timer >= LONG_PRESS_TIME {
+                self.touch = None;
+                self.touch_press(MouseButton::Right);
+            }
+        }
+    }
+
+    fn touch_press(&mut self, button: MouseButton) {
+        self.mouse_buttons.insert(button, (true, true, false));
+        self.touch_release = Some(button);
+    }
+
+    // Lets a widget be tabbed to, returning whether it has the focus.
+    // uses_enter is whether enter does something to it, if it doesn't then enter goes to the default button instead
+    pub fn focusable(&mut self, id: Id, button_state: &ButtonState, uses_enter: bool) -> bool {
+        if button_state.clicked() {
+            if self.focus != Some(id) {
+                self.focus_visible = false;
+            }
+            self.focus = Some(id);
+            self.focus_clicked = true;
+        }
+        if self.focus == Some(id) {
+            self.focus_drawn = true;
+            self.focus_uses_enter = uses_enter;
+        }
+        if self.focus_scope {
+            self.focus_order.push(id);
+        }
+        self.focus == Some(id)
+    }
+    pub fn focus_ring(&self, id: Id) -> bool {
+        self.focus == Some(id) && self.focus_visible
+    }
+    // Whether the focused widget was pressed with the keyboard
+    pub fn focus_pressed(&mut self, id: Id) -> bool {
+        self.focus == Some(id) && std::mem::take(&mut self.focus_pressed)
+    }
+    // Whether enter was pressed for the front popup's default button
+    pub fn default_pressed(&mut self) -> bool {
+        self.focus_scope && self.use_key(|s| &mut s.default_pressed)
+    }
+    pub fn escape_pressed(&mut self) -> bool {
+        self.use_key(|s| &mut s.escape_pressed)
+    }
+    fn use_key(&mut self, key: fn(&mut Self) -> &mut bool) -> bool {
+        let pressed = std::mem::take(key(self));
+        self.keys_used |= pressed;
+        pressed
+    }
+    // Whether the keyboard belongs to the ui this frame, rather than the minefield
+    pub fn keyboard_captured(&self) -> bool {
+        self.text_field.is_some() || self.focus.is_some() || self.keys_used
+    }
+    // For when something wants every key, like when picking one to bind, so enter and space don't press anything either
+    pub fn capture_keyboard(&mut self) {
+        self.keys_used = true;
+        self.focus_pressed = false;
+        self.default_pressed = false;
+    }
+
     pub fn key_held(&mut self) -> Option<KeyCode> {
         match (self.key_held, self.key_hold_timer > 0.5) {
             (Some(k), true) => { self.key_hold_timer -= 0.025; Some(k) },
@@ -201,7 +378,35 @@ impl State {
     }
 
     pub fn finish(&mut self) {
-        match self.mouse_down(MouseButton::Left) {
+        if !std::mem::take(&mut self.text_field_drawn) {
+            self.text_field = None;
+        }
+        if !std::mem::take(&mut self.select_drawn) {
+            self.select = None;
+        }
+        // Clicking anywhere that can't be focused loses the focus, and so does the focused widget disappearing
+        if !std::mem::take(&mut self.focus_drawn) || (self.mouse_pressed(MouseButton::Left) && !self.focus_clicked) {
+            self.focus = None;
+        }
+        self.focus_clicked = false;
+        if self.tabbed && !self.focus_order.is_empty() {
+            let len = self.focus_order.len();
+            let backwards = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+            let next = match (self.focus.and_then(|f| self.focus_order.iter().position(|o| *o == f)), backwards) {
+                (Some(i), false) => (i + 1) % len,
+                (Some(i), true)  => (i + len - 1) % len,
+                (None,    false) => 0,
+                (None,    true)  => len - 1,
+            };
+            self.focus = Some(self.focus_order[next]);
+            self.focus_visible = true;
+            // Whatever was being typed into or picked from isn't any more
+            self.text_field = None;
+            self.select = None;
+        }
+        // Any button, as the minefield can be dug or flagged with whichever ones are bound
+        let any_mouse_down = [MouseButton::Left, MouseButton::Right, MouseButton::Middle].into_iter().any(|b| self.mouse_down(b));
+        match any_mouse_down {
             false => self.active_item = SelectedItem::None,
             true if self.active_item == SelectedItem::None => self.active_item = SelectedItem::Unavailable,
             _ => {}